@@ -0,0 +1,87 @@
+//! Point queries against the AABB picking grid with up to 100k pickable cells.
+//!
+//! Run with `cargo bench --bench aabb_picking`.
+
+use bevy::{
+    math::{Ray, Vec3A},
+    prelude::*,
+    render::primitives::Aabb,
+};
+use bevy_aabb_picking::AabbGrid;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+// A square of 16x16 wall cells like the ones spawned in edit mode.
+fn grid_of(cells: u32) -> (AabbGrid, f32) {
+    let side = (cells as f32).sqrt().ceil() as u32;
+    let aabb = Aabb {
+        half_extents: Vec3A::new(8., 8., 0.) * 0.95,
+        ..default()
+    };
+    let mut grid = AabbGrid::default();
+    for i in 0..cells {
+        let translation = Vec3::new(
+            (i % side) as f32 * 16. + 8.,
+            (i / side) as f32 * 16. + 8.,
+            0.,
+        );
+        grid.insert(
+            Entity::from_raw(i),
+            &aabb,
+            &GlobalTransform::from_translation(translation),
+            true,
+        );
+    }
+    (grid, side as f32 * 16.)
+}
+
+fn query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aabb_grid_query");
+    for cells in [1_000, 10_000, 100_000] {
+        let (grid, size) = grid_of(cells);
+        group.bench_with_input(BenchmarkId::from_parameter(cells), &size, |b, size| {
+            let mut i = 0u32;
+            b.iter(|| {
+                // walk the whole level with a cheap deterministic sequence of points
+                i = i.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let point = Vec2::new(
+                    (i >> 16) as f32 / 65_536. * size,
+                    (i & 0xffff) as f32 / 65_536. * size,
+                );
+                black_box(grid.cast_ray(black_box(Ray {
+                    origin: point.extend(1000.),
+                    direction: Vec3::NEG_Z,
+                })))
+            });
+        });
+    }
+    group.finish();
+}
+
+fn update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aabb_grid_update");
+    for cells in [1_000, 10_000, 100_000] {
+        let (mut grid, _) = grid_of(cells);
+        let aabb = Aabb {
+            half_extents: Vec3A::new(8., 8., 0.),
+            ..default()
+        };
+        group.bench_with_input(BenchmarkId::from_parameter(cells), &cells, |b, cells| {
+            let mut i = 0u32;
+            b.iter(|| {
+                // move one cell around, as a sprite walking through the level would
+                i = (i + 1) % cells;
+                let translation = Vec3::new((i % 97) as f32 * 16., (i % 89) as f32 * 16., 1.);
+                grid.insert(
+                    Entity::from_raw(i),
+                    &aabb,
+                    &GlobalTransform::from_translation(translation),
+                    true,
+                );
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, query, update);
+criterion_main!(benches);
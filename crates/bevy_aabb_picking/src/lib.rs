@@ -0,0 +1,416 @@
+//! A [`bevy_mod_picking`] backend picking entities by their axis-aligned bounding box.
+//!
+//! Entities with an [`Aabb`] are picked by casting the pointer ray against the rectangle of the
+//! box in the entity's local XY plane, so translated, rotated, scaled and tilted sprites, as
+//! well as boxes centred away from the entity origin, are all hit where they are drawn. Hits
+//! carry the world space position and the surface normal facing the camera.
+//!
+//! Pickable boxes are kept in an [`AabbGrid`], a uniform grid over their world space bounds, so
+//! a pointer only has to be tested against the few entities in the grid cells its ray crosses.
+//! The grid is indexed along the world XY plane: the ray of a 2D camera looking down the Z axis
+//! stays in a single cell, tilted and perspective rays walk every cell under them.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use bevy::{prelude::*, render::primitives::Aabb};
+//! use bevy_aabb_picking::AabbPickingBackend;
+//! use bevy_mod_picking::prelude::*;
+//!
+//! App::new()
+//!     .add_plugins((DefaultPlugins, DefaultPickingPlugins, AabbPickingBackend))
+//!     .add_systems(Startup, |mut commands: Commands| {
+//!         commands.spawn(Camera2dBundle::default());
+//!         commands.spawn((
+//!             SpatialBundle::default(),
+//!             Aabb::from_min_max(Vec3::new(-8., -8., 0.), Vec3::new(8., 8., 0.)),
+//!             PickableBundle::default(),
+//!         ));
+//!     })
+//!     .run();
+//! ```
+//!
+//! Sprites get an [`Aabb`] computed by Bevy and are pickable as soon as they are spawned.
+
+#![allow(clippy::type_complexity)]
+#![allow(clippy::too_many_arguments)]
+#![deny(missing_docs)]
+
+use std::cmp::Ordering;
+
+use bevy::{
+    math::{Affine3A, Ray, Vec3A},
+    prelude::*,
+    render::primitives::Aabb,
+    utils::{HashMap, HashSet},
+    window::PrimaryWindow,
+};
+use bevy_mod_picking::{
+    backend::{HitData, PointerHits},
+    picking_core::{PickSet, Pickable},
+    pointer::{PointerId, PointerLocation},
+};
+
+/// Adds the [`AabbGrid`] and the picking backend using it.
+#[derive(Clone)]
+pub struct AabbPickingBackend;
+
+impl Plugin for AabbPickingBackend {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AabbGrid>().add_systems(
+            PreUpdate,
+            (
+                update_aabb_grid.before(PickSet::Backend),
+                aabb_picking.in_set(PickSet::Backend),
+            ),
+        );
+    }
+}
+
+/// A pickable box under a ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AabbHit {
+    /// The entity the box belongs to.
+    pub entity: Entity,
+    /// Distance from the ray origin to the hit.
+    pub depth: f32,
+    /// Hit position in world space.
+    pub position: Vec3,
+    /// Normal of the box plane at the hit in world space, facing the ray origin.
+    pub normal: Vec3,
+}
+
+/// Spatial index of the pickable [`Aabb`]s, bucketed by the grid cells their world space
+/// bounds overlap.
+#[derive(Resource)]
+pub struct AabbGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+    entries: HashMap<Entity, GridEntry>,
+    // cells spanned by every entity inserted since the grid was last empty
+    bounds: Option<(IVec2, IVec2)>,
+}
+
+struct GridEntry {
+    min_cell: IVec2,
+    max_cell: IVec2,
+    affine: Affine3A,
+    inverse: Affine3A,
+    center: Vec2,
+    half_extents: Vec2,
+    should_block_lower: bool,
+}
+
+impl Default for AabbGrid {
+    fn default() -> Self {
+        AabbGrid::new(64.)
+    }
+}
+
+impl AabbGrid {
+    /// Creates an empty grid with cells `cell_size` world units wide. It should be a few times
+    /// larger than the typical pickable entity.
+    pub fn new(cell_size: f32) -> Self {
+        AabbGrid {
+            cell_size,
+            cells: HashMap::default(),
+            entries: HashMap::default(),
+            bounds: None,
+        }
+    }
+
+    /// Number of entities in the grid.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the grid has no entities.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn cell(&self, point: Vec2) -> IVec2 {
+        (point / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Adds `entity` or updates it if it already is in the grid. Only the XY rectangle of the
+    /// `aabb` is pickable, in the local space of `transform`.
+    pub fn insert(
+        &mut self,
+        entity: Entity,
+        aabb: &Aabb,
+        transform: &GlobalTransform,
+        should_block_lower: bool,
+    ) {
+        self.remove(entity);
+
+        let affine = transform.affine();
+        let center = aabb.center.xy();
+        let half_extents = aabb.half_extents.xy();
+        let (min, max) = [
+            Vec2::new(-1., -1.),
+            Vec2::new(1., -1.),
+            Vec2::new(-1., 1.),
+            Vec2::new(1., 1.),
+        ]
+        .iter()
+        .map(|corner| {
+            affine
+                .transform_point3((center + *corner * half_extents).extend(0.))
+                .truncate()
+        })
+        .fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), corner| (min.min(corner), max.max(corner)),
+        );
+        // a zero scale collapses the box, it can't be hit
+        if !min.is_finite() || !max.is_finite() || affine.matrix3.determinant() == 0. {
+            return;
+        }
+
+        let entry = GridEntry {
+            min_cell: self.cell(min),
+            max_cell: self.cell(max),
+            affine,
+            inverse: affine.inverse(),
+            center,
+            half_extents,
+            should_block_lower,
+        };
+        for x in entry.min_cell.x..=entry.max_cell.x {
+            for y in entry.min_cell.y..=entry.max_cell.y {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+            }
+        }
+        self.bounds = Some(match self.bounds {
+            Some((min, max)) => (min.min(entry.min_cell), max.max(entry.max_cell)),
+            None => (entry.min_cell, entry.max_cell),
+        });
+        self.entries.insert(entity, entry);
+    }
+
+    /// Removes `entity` from the grid if it is in it.
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(entry) = self.entries.remove(&entity) else {
+            return;
+        };
+        for x in entry.min_cell.x..=entry.max_cell.x {
+            for y in entry.min_cell.y..=entry.max_cell.y {
+                let cell = IVec2::new(x, y);
+                if let Some(entities) = self.cells.get_mut(&cell) {
+                    entities.retain(|e| *e != entity);
+                    if entities.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            }
+        }
+        if self.entries.is_empty() {
+            self.bounds = None;
+        }
+    }
+
+    // Cells crossed by the XY projection of `ray` inside the grid bounds, in order along the ray.
+    fn cells_along(&self, ray: Ray) -> Vec<IVec2> {
+        let Some((min, max)) = self.bounds else {
+            return vec![];
+        };
+        // in cell units, `t` is the distance along the ray like for `Ray::get_point`
+        let origin = ray.origin.truncate() / self.cell_size;
+        let direction = ray.direction.truncate() / self.cell_size;
+        let in_bounds = |cell: IVec2| cell.cmpge(min).all() && cell.cmple(max).all();
+
+        // looking straight down the Z axis, or close enough to never leave the cell
+        if direction.abs().max_element() < 1e-6 {
+            let cell = origin.floor().as_ivec2();
+            return if in_bounds(cell) { vec![cell] } else { vec![] };
+        }
+
+        // clip the ray to the bounds
+        let (low, high) = (min.as_vec2(), (max + IVec2::ONE).as_vec2());
+        let mut t_enter = 0f32;
+        let mut t_exit = f32::INFINITY;
+        for axis in 0..2 {
+            if direction[axis] == 0. {
+                if origin[axis] < low[axis] || origin[axis] >= high[axis] {
+                    return vec![];
+                }
+                continue;
+            }
+            let (a, b) = (
+                (low[axis] - origin[axis]) / direction[axis],
+                (high[axis] - origin[axis]) / direction[axis],
+            );
+            t_enter = t_enter.max(a.min(b));
+            t_exit = t_exit.min(a.max(b));
+        }
+        if t_enter > t_exit {
+            return vec![];
+        }
+
+        // walk from cell to cell, crossing whichever boundary comes first
+        let start = origin + direction * t_enter;
+        let mut cell = start.floor().as_ivec2().clamp(min, max);
+        let step = direction.signum().as_ivec2();
+        let t_delta = direction.abs().recip();
+        let mut t_next = Vec2::ZERO;
+        for axis in 0..2 {
+            t_next[axis] = if direction[axis] > 0. {
+                t_enter + ((cell[axis] + 1) as f32 - start[axis]) * t_delta[axis]
+            } else if direction[axis] < 0. {
+                t_enter + (start[axis] - cell[axis] as f32) * t_delta[axis]
+            } else {
+                f32::INFINITY
+            };
+        }
+        let mut cells = vec![];
+        while in_bounds(cell) {
+            cells.push(cell);
+            let axis = if t_next.x < t_next.y { 0 } else { 1 };
+            if t_next[axis] > t_exit {
+                break;
+            }
+            cell[axis] += step[axis];
+            t_next[axis] += t_delta[axis];
+        }
+        cells
+    }
+
+    /// Boxes hit by `ray`, nearest first, stopping at the first one that blocks lower entities.
+    ///
+    /// The candidates are looked up in the grid cells crossed by the ray, so it can point in any
+    /// direction.
+    pub fn cast_ray(&self, ray: Ray) -> Vec<AabbHit> {
+        let mut candidates = HashSet::default();
+        let mut hits: Vec<_> = self
+            .cells_along(ray)
+            .iter()
+            .filter_map(|cell| self.cells.get(cell))
+            .flatten()
+            .filter(|entity| candidates.insert(**entity))
+            .filter_map(|entity| {
+                let entry = &self.entries[entity];
+
+                // normals go through the inverse transpose to stay perpendicular when scaled
+                let mut normal: Vec3 = (entry.inverse.matrix3.transpose() * Vec3A::Z)
+                    .normalize()
+                    .into();
+                let origin = entry.affine.transform_point3(entry.center.extend(0.));
+                let depth = ray.intersect_plane(origin, normal)?;
+                let position = ray.get_point(depth);
+
+                // Transform hit pos to sprite coordinate system
+                let local = entry.inverse.transform_point3(position).truncate();
+                let rect = Rect::from_center_half_size(entry.center, entry.half_extents);
+                if !rect.contains(local) {
+                    return None;
+                }
+
+                if normal.dot(ray.direction) > 0. {
+                    normal = -normal;
+                }
+                Some((
+                    AabbHit {
+                        entity: *entity,
+                        depth,
+                        position,
+                        normal,
+                    },
+                    entry.should_block_lower,
+                ))
+            })
+            .collect();
+        hits.sort_by(|a, b| a.0.depth.partial_cmp(&b.0.depth).unwrap_or(Ordering::Equal));
+
+        let mut picks = Vec::with_capacity(hits.len());
+        for (hit, should_block_lower) in hits {
+            picks.push(hit);
+            if should_block_lower {
+                break;
+            }
+        }
+        picks
+    }
+}
+
+/// Keeps the [`AabbGrid`] up to date with added, changed and removed [`Aabb`]s.
+pub fn update_aabb_grid(
+    mut grid: ResMut<AabbGrid>,
+    changed: Query<
+        (Entity, &Aabb, &GlobalTransform, Option<&Pickable>),
+        Or<(Changed<Aabb>, Changed<GlobalTransform>, Changed<Pickable>)>,
+    >,
+    aabbs: Query<(Entity, &Aabb, &GlobalTransform, Option<&Pickable>)>,
+    mut removed_aabbs: RemovedComponents<Aabb>,
+    mut removed_pickables: RemovedComponents<Pickable>,
+) {
+    for entity in removed_aabbs.read() {
+        grid.remove(entity);
+    }
+    for (entity, aabb, transform, pickable) in changed.iter().chain(
+        removed_pickables
+            .read()
+            .filter_map(|entity| aabbs.get(entity).ok()),
+    ) {
+        grid.insert(
+            entity,
+            aabb,
+            transform,
+            pickable.map(|p| p.should_block_lower) != Some(false),
+        );
+    }
+}
+
+/// Reports the [`Aabb`]s under each pointer, once for every camera rendering to the pointer's
+/// window or image whose viewport contains it.
+pub fn aabb_picking(
+    pointers: Query<(&PointerId, &PointerLocation)>,
+    cameras: Query<(Entity, &Camera, &GlobalTransform)>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    grid: Res<AabbGrid>,
+    mut output: EventWriter<PointerHits>,
+) {
+    // there may be no primary window, cameras targeting it are then skipped
+    let primary_window = primary_window.get_single().ok();
+
+    for (pointer, location) in pointers.iter().filter_map(|(pointer, pointer_location)| {
+        pointer_location.location().map(|loc| (pointer, loc))
+    }) {
+        for (cam_entity, camera, cam_transform) in cameras
+            .iter()
+            .filter(|(_, camera, _)| camera.is_active)
+            .filter(|(_, camera, _)| {
+                camera.target.normalize(primary_window).as_ref() == Some(&location.target)
+            })
+        {
+            let Some(viewport) = camera.logical_viewport_rect() else {
+                continue;
+            };
+            if !viewport.contains(location.position) {
+                continue;
+            }
+
+            // pointer positions are relative to the render target, the camera expects them
+            // relative to its viewport
+            let Some(ray) =
+                camera.viewport_to_world(cam_transform, location.position - viewport.min)
+            else {
+                continue;
+            };
+
+            let picks: Vec<(Entity, HitData)> = grid
+                .cast_ray(ray)
+                .into_iter()
+                .map(|hit| {
+                    (
+                        hit.entity,
+                        HitData::new(cam_entity, hit.depth, Some(hit.position), Some(hit.normal)),
+                    )
+                })
+                .collect();
+
+            let order = camera.order as f32;
+            output.send(PointerHits::new(*pointer, picks, order))
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use std::f32::consts::{FRAC_PI_3, FRAC_PI_4};
+
+use bevy::{
+    math::{Ray, Vec3A},
+    prelude::*,
+    render::{
+        camera::{camera_system, ManualTextureViews, RenderTarget, Viewport},
+        primitives::Aabb,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    window::{PrimaryWindow, WindowCreated, WindowRef, WindowResized, WindowResolution},
+};
+use bevy_aabb_picking::{aabb_picking, update_aabb_grid, AabbGrid};
+use bevy_mod_picking::{
+    backend::PointerHits,
+    pointer::{Location, PointerId, PointerLocation},
+};
+
+fn square(half_size: f32) -> Aabb {
+    Aabb {
+        center: Vec3A::ZERO,
+        half_extents: Vec3A::new(half_size, half_size, 0.),
+    }
+}
+
+// A ray from a 2D camera at the default height.
+fn ray(x: f32, y: f32) -> Ray {
+    Ray {
+        origin: Vec3::new(x, y, 999.9),
+        direction: Vec3::NEG_Z,
+    }
+}
+
+fn grid_with(aabb: &Aabb, transform: Transform) -> (AabbGrid, Entity) {
+    let entity = Entity::from_raw(1);
+    let mut grid = AabbGrid::default();
+    grid.insert(entity, aabb, &GlobalTransform::from(transform), true);
+    (grid, entity)
+}
+
+#[test]
+fn translated() {
+    let (grid, entity) = grid_with(&square(8.), Transform::from_xyz(100., 50., 3.));
+
+    let hits = grid.cast_ray(ray(105., 45.));
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].entity, entity);
+    assert!(hits[0].position.abs_diff_eq(Vec3::new(105., 45., 3.), 1e-3));
+    assert!(hits[0].normal.abs_diff_eq(Vec3::Z, 1e-5));
+    assert!((hits[0].depth - 996.9).abs() < 1e-3);
+
+    assert!(grid.cast_ray(ray(109., 50.)).is_empty());
+    assert!(grid.cast_ray(ray(0., 0.)).is_empty());
+}
+
+#[test]
+fn offset_center() {
+    let aabb = Aabb {
+        center: Vec3A::new(20., 0., 0.),
+        half_extents: Vec3A::new(4., 4., 0.),
+    };
+    let (grid, _) = grid_with(&aabb, Transform::from_xyz(10., 0., 0.));
+
+    assert_eq!(grid.cast_ray(ray(32., 3.)).len(), 1);
+    assert!(grid.cast_ray(ray(10., 0.)).is_empty());
+}
+
+#[test]
+fn rotated() {
+    let transform = Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_4));
+    let (grid, _) = grid_with(&square(8.), transform);
+
+    // the corners of the unrotated square are outside, the rotated corners are inside
+    assert!(grid.cast_ray(ray(7., 7.)).is_empty());
+    assert_eq!(grid.cast_ray(ray(11., 0.)).len(), 1);
+    assert_eq!(grid.cast_ray(ray(0., -11.)).len(), 1);
+}
+
+#[test]
+fn scaled() {
+    let (grid, _) = grid_with(&square(8.), Transform::from_scale(Vec3::new(2., 0.5, 1.)));
+
+    assert_eq!(grid.cast_ray(ray(15., 0.)).len(), 1);
+    assert!(grid.cast_ray(ray(17., 0.)).is_empty());
+    assert_eq!(grid.cast_ray(ray(0., 3.)).len(), 1);
+    assert!(grid.cast_ray(ray(0., 5.)).is_empty());
+}
+
+#[test]
+fn flipped() {
+    let (grid, _) = grid_with(&square(8.), Transform::from_scale(Vec3::new(-1., 1., -1.)));
+
+    let hits = grid.cast_ray(ray(4., 4.));
+    assert_eq!(hits.len(), 1);
+    assert!(hits[0].normal.abs_diff_eq(Vec3::Z, 1e-5));
+}
+
+#[test]
+fn zero_scale() {
+    let (grid, _) = grid_with(&square(8.), Transform::from_scale(Vec3::ZERO));
+
+    assert!(grid.is_empty());
+    assert!(grid.cast_ray(ray(0., 0.)).is_empty());
+}
+
+#[test]
+fn tilted() {
+    // leaning back by 60 degrees, the square looks half as tall from above
+    let transform = Transform::from_rotation(Quat::from_rotation_x(FRAC_PI_3));
+    let (grid, _) = grid_with(&square(8.), transform);
+
+    let hits = grid.cast_ray(ray(0., 3.));
+    assert_eq!(hits.len(), 1);
+    assert!(hits[0]
+        .position
+        .abs_diff_eq(Vec3::new(0., 3., 3. * 3f32.sqrt()), 1e-3));
+    assert!(hits[0]
+        .normal
+        .abs_diff_eq(Vec3::new(0., -FRAC_PI_3.sin(), FRAC_PI_3.cos()), 1e-5));
+
+    assert!(grid.cast_ray(ray(0., 5.)).is_empty());
+}
+
+#[test]
+fn oblique() {
+    // far from the cell of the ray origin, like through a perspective camera
+    let (grid, entity) = grid_with(&square(8.), Transform::from_xyz(200., -130., 0.));
+
+    let origin = Vec3::new(-50., 20., 100.);
+    let target = Vec3::new(203., -127., 0.);
+    let hits = grid.cast_ray(Ray {
+        origin,
+        direction: (target - origin).normalize(),
+    });
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].entity, entity);
+    assert!(hits[0].position.abs_diff_eq(target, 1e-3));
+
+    // passing next to the box, and pointing away from it
+    let miss = Vec3::new(220., -130., 0.);
+    assert!(grid
+        .cast_ray(Ray {
+            origin,
+            direction: (miss - origin).normalize(),
+        })
+        .is_empty());
+    assert!(grid
+        .cast_ray(Ray {
+            origin,
+            direction: (origin - target).normalize(),
+        })
+        .is_empty());
+}
+
+#[test]
+fn nearest_first_and_blocking() {
+    let mut grid = AabbGrid::default();
+    let back = Entity::from_raw(1);
+    let middle = Entity::from_raw(2);
+    let front = Entity::from_raw(3);
+    grid.insert(
+        back,
+        &square(8.),
+        &Transform::from_xyz(0., 0., 0.).into(),
+        true,
+    );
+    grid.insert(
+        middle,
+        &square(8.),
+        &Transform::from_xyz(0., 0., 1.).into(),
+        true,
+    );
+    grid.insert(
+        front,
+        &square(8.),
+        &Transform::from_xyz(0., 0., 2.).into(),
+        false,
+    );
+
+    let hits: Vec<_> = grid
+        .cast_ray(ray(0., 0.))
+        .iter()
+        .map(|hit| hit.entity)
+        .collect();
+    assert_eq!(hits, [front, middle]);
+}
+
+#[test]
+fn moved_and_removed() {
+    let (mut grid, entity) = grid_with(&square(8.), Transform::default());
+
+    grid.insert(
+        entity,
+        &square(8.),
+        &Transform::from_xyz(500., 0., 0.).into(),
+        true,
+    );
+    assert_eq!(grid.len(), 1);
+    assert!(grid.cast_ray(ray(0., 0.)).is_empty());
+    assert_eq!(grid.cast_ray(ray(500., 0.)).len(), 1);
+
+    grid.remove(entity);
+    assert!(grid.is_empty());
+    assert!(grid.cast_ray(ray(500., 0.)).is_empty());
+}
+
+#[test]
+fn follows_the_world() {
+    let mut app = App::new();
+    app.init_resource::<AabbGrid>()
+        .add_systems(Update, update_aabb_grid);
+
+    let entity = app
+        .world
+        .spawn((
+            square(8.),
+            GlobalTransform::from(Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_4))),
+        ))
+        .id();
+    app.update();
+    assert_eq!(
+        app.world
+            .resource::<AabbGrid>()
+            .cast_ray(ray(11., 0.))
+            .len(),
+        1
+    );
+
+    *app.world.get_mut::<GlobalTransform>(entity).unwrap() =
+        Transform::from_xyz(100., 0., 0.).into();
+    app.update();
+    let grid = app.world.resource::<AabbGrid>();
+    assert!(grid.cast_ray(ray(11., 0.)).is_empty());
+    assert_eq!(grid.cast_ray(ray(100., 0.)).len(), 1);
+
+    app.world.despawn(entity);
+    app.update();
+    assert!(app.world.resource::<AabbGrid>().is_empty());
+}
+
+// Runs the backend with the camera bookkeeping of the renderer, but without rendering.
+fn picking_app() -> App {
+    let mut app = App::new();
+    app.init_resource::<AabbGrid>()
+        .init_resource::<Assets<Image>>()
+        .init_resource::<ManualTextureViews>()
+        .add_event::<WindowCreated>()
+        .add_event::<WindowResized>()
+        .add_event::<AssetEvent<Image>>()
+        .add_event::<PointerHits>()
+        .add_systems(
+            Update,
+            (
+                camera_system::<OrthographicProjection>,
+                update_aabb_grid,
+                aabb_picking,
+            )
+                .chain(),
+        );
+    app
+}
+
+fn window(app: &mut App, primary: bool) -> Entity {
+    let mut window = app.world.spawn(Window {
+        resolution: WindowResolution::new(800., 600.),
+        ..default()
+    });
+    if primary {
+        window.insert(PrimaryWindow);
+    }
+    window.id()
+}
+
+fn camera(
+    app: &mut App,
+    target: RenderTarget,
+    viewport: Option<Viewport>,
+    order: isize,
+    looking_at: Vec2,
+) -> Entity {
+    app.world
+        .spawn(Camera2dBundle {
+            camera: Camera {
+                target,
+                viewport,
+                order,
+                ..default()
+            },
+            global_transform: Transform::from_xyz(looking_at.x, looking_at.y, 999.9).into(),
+            ..default()
+        })
+        .id()
+}
+
+fn pickable(app: &mut App, x: f32, y: f32) -> Entity {
+    app.world
+        .spawn((square(8.), GlobalTransform::from_xyz(x, y, 0.)))
+        .id()
+}
+
+fn pointer(app: &mut App, id: u64, target: RenderTarget, position: Vec2) {
+    let target = target.normalize(None).unwrap();
+    app.world.spawn((
+        PointerId::Touch(id),
+        PointerLocation {
+            location: Some(Location { target, position }),
+        },
+    ));
+}
+
+// The camera and picked entities of each report, by pointer.
+fn reports(app: &App, id: u64) -> Vec<(Entity, Vec<Entity>)> {
+    app.world
+        .resource::<Events<PointerHits>>()
+        .iter_current_update_events()
+        .filter(|hits| hits.pointer == PointerId::Touch(id))
+        .map(|hits| {
+            let camera = hits.picks.first().map(|(_, hit)| hit.camera);
+            (
+                camera.unwrap_or(Entity::PLACEHOLDER),
+                hits.picks.iter().map(|(entity, _)| *entity).collect(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn secondary_window() {
+    let mut app = picking_app();
+    let primary = window(&mut app, true);
+    let secondary = window(&mut app, false);
+    let primary_camera = camera(
+        &mut app,
+        RenderTarget::Window(WindowRef::Primary),
+        None,
+        0,
+        Vec2::ZERO,
+    );
+    let secondary_camera = camera(
+        &mut app,
+        RenderTarget::Window(WindowRef::Entity(secondary)),
+        None,
+        0,
+        Vec2::new(1000., 0.),
+    );
+    let near_origin = pickable(&mut app, 0., 0.);
+    let far_away = pickable(&mut app, 1000., 0.);
+    let center = Vec2::new(400., 300.);
+    pointer(
+        &mut app,
+        0,
+        RenderTarget::Window(WindowRef::Entity(primary)),
+        center,
+    );
+    pointer(
+        &mut app,
+        1,
+        RenderTarget::Window(WindowRef::Entity(secondary)),
+        center,
+    );
+    app.update();
+
+    // each pointer is only tested against the camera of its own window
+    assert_eq!(reports(&app, 0), [(primary_camera, vec![near_origin])]);
+    assert_eq!(reports(&app, 1), [(secondary_camera, vec![far_away])]);
+}
+
+#[test]
+fn image_target() {
+    let mut app = picking_app();
+    window(&mut app, true);
+    let image = app
+        .world
+        .resource_mut::<Assets<Image>>()
+        .add(Image::new_fill(
+            Extent3d {
+                width: 128,
+                height: 128,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Rgba8UnormSrgb,
+        ));
+    let image_camera = camera(
+        &mut app,
+        RenderTarget::Image(image.clone()),
+        None,
+        0,
+        Vec2::new(-500., 0.),
+    );
+    camera(
+        &mut app,
+        RenderTarget::Window(WindowRef::Primary),
+        None,
+        0,
+        Vec2::ZERO,
+    );
+    let entity = pickable(&mut app, -500., 0.);
+    pickable(&mut app, 0., 0.);
+    pointer(
+        &mut app,
+        0,
+        RenderTarget::Image(image.clone()),
+        Vec2::splat(64.),
+    );
+    // outside of the image
+    pointer(&mut app, 1, RenderTarget::Image(image), Vec2::splat(200.));
+    app.update();
+
+    assert_eq!(reports(&app, 0), [(image_camera, vec![entity])]);
+    assert!(reports(&app, 1).is_empty());
+}
+
+#[test]
+fn viewports() {
+    let mut app = picking_app();
+    let primary = window(&mut app, true);
+    let viewport = |x: u32| Viewport {
+        physical_position: UVec2::new(x, 0),
+        physical_size: UVec2::new(400, 600),
+        ..default()
+    };
+    let left_camera = camera(
+        &mut app,
+        RenderTarget::Window(WindowRef::Primary),
+        Some(viewport(0)),
+        0,
+        Vec2::ZERO,
+    );
+    let right_camera = camera(
+        &mut app,
+        RenderTarget::Window(WindowRef::Primary),
+        Some(viewport(400)),
+        1,
+        Vec2::new(1000., 0.),
+    );
+    let left = pickable(&mut app, 0., 0.);
+    let right = pickable(&mut app, 1000., 0.);
+    let target = RenderTarget::Window(WindowRef::Entity(primary));
+    // the centers of the two viewports
+    pointer(&mut app, 0, target.clone(), Vec2::new(200., 300.));
+    pointer(&mut app, 1, target, Vec2::new(600., 300.));
+    app.update();
+
+    assert_eq!(reports(&app, 0), [(left_camera, vec![left])]);
+    assert_eq!(reports(&app, 1), [(right_camera, vec![right])]);
+}
@@ -0,0 +1,235 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::{BoxedFuture, HashMap},
+};
+use bevy_ecs_ldtk::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{audio::AudioEvent, GameMode, PauseState};
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<AnimationSheet>()
+            .init_asset_loader::<AnimationSheetLoader>()
+            .add_systems(Update, load_sheets)
+            // after the gameplay systems have picked the states of this frame, and under the lose
+            // screen for the player's hurt clip
+            .add_systems(
+                PostUpdate,
+                animate
+                    .run_if(in_state(GameMode::Play).or_else(in_state(GameMode::Lost)))
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Run,
+    Climb,
+    Jump,
+    Fall,
+    Hurt,
+}
+
+/// The clips of a sprite sheet, loaded from a `.anim.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct AnimationSheet {
+    pub clips: HashMap<AnimationState, AnimationClip>,
+}
+
+impl AnimationSheet {
+    /// States without a clip of their own play the idle one.
+    pub fn clip(&self, state: AnimationState) -> Option<&AnimationClip> {
+        self.clips
+            .get(&state)
+            .or_else(|| self.clips.get(&AnimationState::Idle))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AnimationClip {
+    /// Indices in the texture atlas.
+    pub frames: Vec<usize>,
+    /// Seconds each frame is shown.
+    #[serde(default = "default_frame_duration")]
+    pub frame_duration: f32,
+    /// One-shot clips stop on their last frame, or go on to `then`.
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+    #[serde(default)]
+    pub then: Option<AnimationState>,
+    /// Sounds played when a frame is shown.
+    #[serde(default)]
+    pub events: Vec<FrameEvent>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FrameEvent {
+    /// Position in the clip's `frames`, not an atlas index.
+    pub frame: usize,
+    /// Name in the sound bank.
+    pub sound: String,
+}
+
+fn default_frame_duration() -> f32 {
+    0.1
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+#[derive(Default)]
+struct AnimationSheetLoader;
+
+#[derive(Error, Debug)]
+enum AnimationSheetLoaderError {
+    #[error("could not read the animation sheet: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the animation sheet: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for AnimationSheetLoader {
+    type Asset = AnimationSheet;
+    type Settings = ();
+    type Error = AnimationSheetLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<AnimationSheet, AnimationSheetLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+/// Drives the `TextureAtlasSprite` of the entity from the clip of its current state.
+#[derive(Component, Clone, Debug)]
+pub struct Animation {
+    /// Path of the animation sheet, loaded when the component is added.
+    pub sheet_path: String,
+    /// Setting a different state starts its clip from the first frame.
+    pub state: AnimationState,
+    /// Playback speed, 0 holds the current frame.
+    pub speed: f32,
+    /// Silences the frame events, like footsteps while swimming.
+    pub muted: bool,
+    sheet: Handle<AnimationSheet>,
+    playing: Option<AnimationState>,
+    elapsed: f32,
+    frame: Option<usize>,
+}
+
+impl Animation {
+    pub fn new(sheet_path: impl Into<String>) -> Self {
+        Animation {
+            sheet_path: sheet_path.into(),
+            state: AnimationState::Idle,
+            speed: 1.0,
+            muted: false,
+            sheet: Handle::default(),
+            playing: None,
+            elapsed: 0.0,
+            frame: None,
+        }
+    }
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation::new("")
+    }
+}
+
+// Each LDtk entity has its sheet named after its identifier, like `animations/player.anim.ron`.
+impl From<&EntityInstance> for Animation {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Animation::new(format!(
+            "animations/{}.anim.ron",
+            entity_instance.identifier.to_lowercase()
+        ))
+    }
+}
+
+fn load_sheets(
+    mut animations: Query<&mut Animation, Added<Animation>>,
+    asset_server: Res<AssetServer>,
+) {
+    for mut animation in &mut animations {
+        if !animation.sheet_path.is_empty() {
+            animation.sheet = asset_server.load(animation.sheet_path.clone());
+        }
+    }
+}
+
+fn animate(
+    mut query: Query<(Entity, &mut Animation, &mut TextureAtlasSprite)>,
+    sheets: Res<Assets<AnimationSheet>>,
+    time: Res<Time>,
+    mut audio_events: EventWriter<AudioEvent>,
+) {
+    for (entity, mut animation, mut sprite) in &mut query {
+        let Some(sheet) = sheets.get(&animation.sheet) else {
+            continue;
+        };
+
+        if animation.playing != Some(animation.state) {
+            animation.playing = Some(animation.state);
+            animation.elapsed = 0.0;
+            animation.frame = None;
+        } else {
+            animation.elapsed += time.delta_seconds() * animation.speed;
+        }
+
+        let Some(clip) = sheet.clip(animation.state) else {
+            continue;
+        };
+        if clip.frames.is_empty() {
+            continue;
+        }
+        let count = clip.frames.len();
+        let frame = (animation.elapsed / clip.frame_duration.max(f32::EPSILON)) as usize;
+        let frame = if clip.looping {
+            frame % count
+        } else if frame >= count {
+            if let Some(then) = clip.then {
+                animation.state = then;
+            }
+            count - 1
+        } else {
+            frame
+        };
+
+        if animation.frame != Some(frame) {
+            animation.frame = Some(frame);
+            if !animation.muted {
+                for event in clip.events.iter().filter(|event| event.frame == frame) {
+                    audio_events.send(AudioEvent::new(event.sound.clone()).with_entity(entity));
+                }
+            }
+        }
+
+        let index = clip.frames[frame];
+        if sprite.index != index {
+            sprite.index = index;
+        }
+    }
+}
@@ -1,117 +1,320 @@
-use bevy::prelude::*;
+use std::borrow::Cow;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    audio::Volume,
+    prelude::*,
+    reflect::TypePath,
+    utils::{BoxedFuture, HashMap},
+};
+use rand::Rng;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::settings::Settings;
 
 pub struct AudioPlugin;
 
 impl Plugin for AudioPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, load_audio)
+        app.init_asset::<SoundBank>()
+            .init_asset_loader::<SoundBankLoader>()
+            .init_resource::<SoundBankState>()
+            .add_systems(Startup, load_audio)
             .add_systems(Update, play_audio)
             .add_event::<AudioEvent>();
     }
 }
 
-#[derive(Event)]
-pub enum AudioEvent {
-    Click,
-    Jump,
-    Fall,
-    Win,
-    Crash,
-    AddCollider,
-    RemoveCollider,
-    FailedCollider,
-    Eagle,
+/// Horizontal gap between the ears of the listener, in pixels.
+pub const EAR_GAP: f32 = 200.0;
+/// Pixels in a unit of distance for spatial audio, sounds within a unit of the listener are not attenuated.
+pub const SPATIAL_SCALE: f32 = 1.0 / 100.0;
+
+/// Plays the sound with this name from the sound bank.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct AudioEvent {
+    pub name: Cow<'static, str>,
+    /// Where the sound comes from, it is heard everywhere the same when `None`.
+    pub emitter: Option<Emitter>,
+    /// Multiplies the volume of the clip, for sounds that depend on how hard something hit.
+    pub volume: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Emitter {
+    /// The current position of an entity with a `GlobalTransform`.
+    Entity(Entity),
+    Position(Vec2),
+}
+
+impl AudioEvent {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        AudioEvent {
+            name: name.into(),
+            emitter: None,
+            volume: 1.0,
+        }
+    }
+
+    pub fn with_entity(mut self, entity: Entity) -> Self {
+        self.emitter = Some(Emitter::Entity(entity));
+        self
+    }
+
+    pub fn with_position(mut self, position: Vec2) -> Self {
+        self.emitter = Some(Emitter::Position(position));
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Sounds by name, loaded from a `.bank.ron` file.
+#[derive(Asset, TypePath, Debug)]
+pub struct SoundBank {
+    pub sounds: HashMap<String, Sound>,
+}
+
+#[derive(Debug)]
+pub struct Sound {
+    pub clips: Vec<Clip>,
+    pub selection: Selection,
+    /// Range of the random speed the clips are played at, which also changes their pitch.
+    pub pitch: (f32, f32),
+    /// Seconds before the sound can play again, events sent in between are dropped.
+    pub cooldown: f32,
+    /// Maximum number of instances playing at once.
+    pub max_instances: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct Clip {
+    pub source: Handle<AudioSource>,
+    pub volume: f32,
+    pub speed: f32,
+}
+
+/// How a sound with several clips picks the one to play.
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+pub enum Selection {
+    /// Any clip, possibly the same one twice in a row.
+    #[default]
+    Random,
+    /// Any clip but the last one played.
+    Shuffle,
+    /// The clips in order, looping back to the first one.
+    Sequence,
+}
+
+// The file format, clips are paths relative to the assets folder.
+#[derive(Deserialize)]
+struct SoundBankFile {
+    sounds: HashMap<String, SoundFile>,
+}
+
+#[derive(Deserialize)]
+struct SoundFile {
+    clips: Vec<ClipFile>,
+    #[serde(default)]
+    selection: Selection,
+    #[serde(default = "default_pitch")]
+    pitch: (f32, f32),
+    #[serde(default)]
+    cooldown: f32,
+    #[serde(default)]
+    max_instances: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ClipFile {
+    path: String,
+    #[serde(default = "default_one")]
+    volume: f32,
+    #[serde(default = "default_one")]
+    speed: f32,
+}
+
+fn default_pitch() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+#[derive(Default)]
+struct SoundBankLoader;
+
+#[derive(Error, Debug)]
+enum SoundBankLoaderError {
+    #[error("could not read the sound bank: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the sound bank: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for SoundBankLoader {
+    type Asset = SoundBank;
+    type Settings = ();
+    type Error = SoundBankLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<SoundBank, SoundBankLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let file: SoundBankFile = ron::de::from_bytes(&bytes)?;
+            let sounds = file
+                .sounds
+                .into_iter()
+                .map(|(name, sound)| {
+                    let clips = sound
+                        .clips
+                        .into_iter()
+                        .map(|clip| Clip {
+                            source: load_context.load(clip.path),
+                            volume: clip.volume,
+                            speed: clip.speed,
+                        })
+                        .collect();
+                    let sound = Sound {
+                        clips,
+                        selection: sound.selection,
+                        pitch: sound.pitch,
+                        cooldown: sound.cooldown,
+                        max_instances: sound.max_instances,
+                    };
+                    (name, sound)
+                })
+                .collect();
+            Ok(SoundBank { sounds })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bank.ron"]
+    }
 }
 
 #[derive(Resource)]
-struct AudioHandles {
-    click: Handle<AudioSource>,
-    jump: Handle<AudioSource>,
-    fall: Handle<AudioSource>,
-    win: Handle<AudioSource>,
-    crash: Handle<AudioSource>,
-    add_collider: Handle<AudioSource>,
-    remove_collider: Handle<AudioSource>,
-    failed_collider: Handle<AudioSource>,
-    eagle: Handle<AudioSource>,
+pub struct SoundBankHandle(pub Handle<SoundBank>);
+
+// When each sound last played and which clip it played.
+#[derive(Resource, Default)]
+struct SoundBankState {
+    last_played: HashMap<String, (f32, usize)>,
 }
 
+#[derive(Component)]
+struct PlayingSound(String);
+
 fn load_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(AudioHandles {
-        click: asset_server.load("click.ogg"),
-        jump: asset_server.load("jump.ogg"),
-        fall: asset_server.load("fall.ogg"),
-        win: asset_server.load("win.ogg"),
-        crash: asset_server.load("crash.ogg"),
-        add_collider: asset_server.load("add.ogg"),
-        remove_collider: asset_server.load("remove.ogg"),
-        failed_collider: asset_server.load("failed.ogg"),
-        eagle: asset_server.load("eagle.ogg"),
-    });
+    commands.insert_resource(SoundBankHandle(asset_server.load("sounds.bank.ron")));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn play_audio(
     mut events: EventReader<AudioEvent>,
     mut commands: Commands,
-    handles: Res<AudioHandles>,
+    bank: Res<SoundBankHandle>,
+    banks: Res<Assets<SoundBank>>,
+    mut state: ResMut<SoundBankState>,
+    playing: Query<&PlayingSound>,
+    transforms: Query<&GlobalTransform>,
+    listener: Query<&GlobalTransform, With<SpatialListener>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
 ) {
+    let Some(bank) = banks.get(&bank.0) else {
+        events.clear();
+        return;
+    };
+    let now = time.elapsed_seconds();
+    // emitters are put at the height of the listener so only the distance in the level counts
+    let listener_z = listener
+        .get_single()
+        .map_or(0.0, |listener| listener.translation().z);
+    let mut rng = rand::thread_rng();
+
     for event in events.read() {
-        match event {
-            AudioEvent::Click => {
-                commands.spawn(AudioBundle {
-                    source: handles.click.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
-            }
-            AudioEvent::Jump => {
-                commands.spawn(AudioBundle {
-                    source: handles.jump.clone(),
-                    settings: PlaybackSettings::DESPAWN
-                        .with_volume(bevy::audio::Volume::new_relative(0.5)),
-                });
-            }
-            AudioEvent::Fall => {
-                commands.spawn(AudioBundle {
-                    source: handles.fall.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
-            }
-            AudioEvent::Win => {
-                commands.spawn(AudioBundle {
-                    source: handles.win.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
-            }
-            AudioEvent::Crash => {
-                commands.spawn(AudioBundle {
-                    source: handles.crash.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
-            }
-            AudioEvent::AddCollider => {
-                commands.spawn(AudioBundle {
-                    source: handles.add_collider.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
-            }
-            AudioEvent::RemoveCollider => {
-                commands.spawn(AudioBundle {
-                    source: handles.remove_collider.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
-            }
-            AudioEvent::FailedCollider => {
-                commands.spawn(AudioBundle {
-                    source: handles.failed_collider.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
+        let Some(sound) = bank.sounds.get(event.name()) else {
+            warn!("no sound named {:?} in the sound bank", event.name());
+            continue;
+        };
+        if sound.clips.is_empty() {
+            continue;
+        }
+
+        let last = state.last_played.get(event.name()).copied();
+        if matches!(last, Some((time, _)) if now - time < sound.cooldown) {
+            continue;
+        }
+        if let Some(max_instances) = sound.max_instances {
+            let instances = playing
+                .iter()
+                .filter(|playing| playing.0 == event.name())
+                .count();
+            if instances >= max_instances {
+                continue;
             }
-            AudioEvent::Eagle => {
-                commands.spawn(AudioBundle {
-                    source: handles.eagle.clone(),
-                    settings: PlaybackSettings::DESPAWN,
-                });
+        }
+
+        let count = sound.clips.len();
+        let index = match (sound.selection, last) {
+            (Selection::Sequence, Some((_, last))) => (last + 1) % count,
+            (Selection::Sequence, None) => 0,
+            (Selection::Shuffle, Some((_, last))) if count > 1 => {
+                (last + rng.gen_range(1..count)) % count
             }
+            _ => rng.gen_range(0..count),
+        };
+        state
+            .last_played
+            .insert(event.name().to_string(), (now, index));
+
+        let clip = &sound.clips[index];
+        let (low, high) = sound.pitch;
+        let pitch = if low < high {
+            rng.gen_range(low..high)
+        } else {
+            low
+        };
+        let position = match event.emitter {
+            Some(Emitter::Entity(entity)) => transforms
+                .get(entity)
+                .ok()
+                .map(|transform| transform.translation().truncate()),
+            Some(Emitter::Position(position)) => Some(position),
+            None => None,
+        };
+        let mut sound = commands.spawn((
+            AudioBundle {
+                source: clip.source.clone(),
+                settings: PlaybackSettings::DESPAWN
+                    .with_speed(clip.speed * pitch)
+                    .with_volume(Volume::new_relative(
+                        clip.volume * event.volume * settings.sfx(),
+                    ))
+                    .with_spatial(position.is_some()),
+            },
+            PlayingSound(event.name().to_string()),
+        ));
+        if let Some(position) = position {
+            sound.insert(TransformBundle::from_transform(
+                Transform::from_translation(position.extend(listener_z)),
+            ));
         }
     }
 }
@@ -0,0 +1,365 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::camera::ScalingMode,
+};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{audio::AudioEvent, components::Player, GameMode};
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraController>()
+            .add_systems(
+                Update,
+                (
+                    follow_player.run_if(in_state(GameMode::Play)),
+                    edit_camera.run_if(in_state(GameMode::Edit)),
+                    add_trauma,
+                    shake,
+                )
+                    .chain(),
+            )
+            .add_systems(OnEnter(GameMode::Edit), reset_edit_camera);
+    }
+}
+
+#[derive(Resource)]
+pub struct CameraController {
+    /// Half size of the box around the camera focus the player can move in without moving it.
+    pub dead_zone: Vec2,
+    /// How far ahead the camera looks, in seconds of the player velocity.
+    pub look_ahead: f32,
+    pub max_look_ahead: Vec2,
+    /// Roughly the time it takes the camera to reach its target.
+    pub smooth_time: f32,
+    /// Size of the grid the camera snaps to, in world units. `None` to disable snapping.
+    pub pixel_snap: Option<f32>,
+    /// Current amount of screen shake, between 0 and 1.
+    pub trauma: f32,
+    /// Trauma lost per second.
+    pub trauma_decay: f32,
+    /// Offset of the camera at full trauma.
+    pub max_shake: Vec2,
+    /// Edit mode panning speed with the keyboard, in world units per second at zoom 1.
+    pub pan_speed: f32,
+    pub min_zoom: f32,
+    /// Zooming out further is still allowed when needed to fit the whole level.
+    pub max_zoom: f32,
+    zoom: f32,
+    fit_requested: bool,
+    editing: Option<String>,
+    focus: Vec2,
+    position: Vec2,
+    velocity: Vec2,
+    tracking: Option<(Entity, String)>,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        CameraController {
+            dead_zone: Vec2::new(24., 32.),
+            look_ahead: 0.25,
+            max_look_ahead: Vec2::new(64., 48.),
+            smooth_time: 0.2,
+            pixel_snap: Some(1.),
+            trauma: 0.,
+            trauma_decay: 1.5,
+            max_shake: Vec2::new(8., 6.),
+            pan_speed: 300.,
+            min_zoom: 0.25,
+            max_zoom: 4.,
+            zoom: 1.,
+            fit_requested: false,
+            editing: None,
+            focus: Vec2::ZERO,
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            tracking: None,
+        }
+    }
+}
+
+impl CameraController {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.);
+    }
+
+    /// Zooms the edit camera out to show the whole level.
+    pub fn fit_level(&mut self) {
+        self.fit_requested = true;
+    }
+}
+
+// Critically damped spring towards `target`, see "Game Programming Gems 4", chapter 1.10.
+fn smooth_damp(
+    current: Vec2,
+    target: Vec2,
+    velocity: &mut Vec2,
+    smooth_time: f32,
+    dt: f32,
+) -> Vec2 {
+    let omega = 2. / smooth_time.max(0.0001);
+    let x = omega * dt;
+    let exp = 1. / (1. + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + omega * change) * dt;
+    *velocity = (*velocity - omega * temp) * exp;
+    target + (change + temp) * exp
+}
+
+// Origin, size and IID of the spawned level.
+#[allow(clippy::type_complexity)]
+fn current_level(
+    level_query: &Query<
+        (&Transform, &LevelIid),
+        (Without<OrthographicProjection>, Without<Player>),
+    >,
+    ldtk_projects: &Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: &Assets<LdtkProject>,
+) -> Option<(Vec2, Vec2, String)> {
+    let (level_transform, level_iid) = level_query.iter().next()?;
+    let ldtk_project = ldtk_project_assets
+        .get(ldtk_projects.single())
+        .expect("Project should be loaded if level has spawned");
+
+    let level = ldtk_project
+        .get_raw_level_by_iid(&level_iid.to_string())
+        .expect("Spawned level should exist in LDtk project");
+
+    Some((
+        level_transform.translation.truncate(),
+        Vec2::new(level.px_wid as f32, level.px_hei as f32),
+        level_iid.to_string(),
+    ))
+}
+
+// Size of the view showing the whole level along its smaller side.
+fn fit_view(level_size: Vec2, window_aspect_ratio: f32) -> Vec2 {
+    if level_size.x / level_size.y > window_aspect_ratio {
+        Vec2::new(level_size.y * window_aspect_ratio, level_size.y)
+    } else {
+        Vec2::new(level_size.x, level_size.x / window_aspect_ratio)
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn follow_player(
+    mut camera_query: Query<&mut OrthographicProjection, Without<Player>>,
+    player_query: Query<(Entity, &Transform, Option<&Velocity>), With<Player>>,
+    level_query: Query<(&Transform, &LevelIid), (Without<OrthographicProjection>, Without<Player>)>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    window: Query<&Window>,
+    mut controller: ResMut<CameraController>,
+    time: Res<Time>,
+) {
+    let Ok((player_entity, player_transform, player_velocity)) = player_query.get_single() else {
+        return;
+    };
+    let Ok(mut orthographic_projection) = camera_query.get_single_mut() else {
+        return;
+    };
+    let Some((level_origin, level_size, level_iid)) =
+        current_level(&level_query, &ldtk_projects, &ldtk_project_assets)
+    else {
+        return;
+    };
+    let window_aspect_ratio = window
+        .get_single()
+        .map(|w| w.width() / w.height())
+        .unwrap_or(1.);
+    let player = player_transform.translation.truncate();
+
+    let view = fit_view(level_size, window_aspect_ratio);
+    orthographic_projection.viewport_origin = Vec2::splat(0.5);
+    orthographic_projection.scaling_mode = ScalingMode::Fixed {
+        width: view.x,
+        height: view.y,
+    };
+    orthographic_projection.scale = 1.;
+
+    // jump straight to the player when they or the level changed instead of gliding there
+    let tracking = Some((player_entity, level_iid));
+    if controller.tracking != tracking {
+        controller.tracking = tracking;
+        controller.focus = player;
+        controller.position = player;
+        controller.velocity = Vec2::ZERO;
+    }
+
+    let offset = player - controller.focus;
+    let dead_zone = controller.dead_zone;
+    controller.focus += offset - offset.clamp(-dead_zone, dead_zone);
+
+    let look_ahead = player_velocity.map_or(Vec2::ZERO, |velocity| {
+        (velocity.linvel * controller.look_ahead)
+            .clamp(-controller.max_look_ahead, controller.max_look_ahead)
+    });
+    let target = controller.focus + look_ahead;
+
+    let CameraController {
+        position,
+        velocity,
+        smooth_time,
+        ..
+    } = controller.as_mut();
+    *position = smooth_damp(
+        *position,
+        target,
+        velocity,
+        *smooth_time,
+        time.delta_seconds(),
+    );
+
+    // keep the view inside the level, centered if the level is smaller than the view
+    let min = level_origin + view / 2.;
+    let max = level_origin + level_size - view / 2.;
+    controller.position = controller.position.clamp(min.min(max), max.max(min));
+}
+
+fn reset_edit_camera(mut controller: ResMut<CameraController>) {
+    controller.editing = None;
+    controller.fit_requested = false;
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn edit_camera(
+    mut camera_query: Query<&mut OrthographicProjection, Without<Player>>,
+    player_query: Query<&Transform, With<Player>>,
+    level_query: Query<(&Transform, &LevelIid), (Without<OrthographicProjection>, Without<Player>)>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    window: Query<&Window>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut controller: ResMut<CameraController>,
+    time: Res<Time>,
+) {
+    let scroll: f32 = mouse_wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 100.,
+        })
+        .sum();
+    let drag: Vec2 = mouse_motion.read().map(|event| event.delta).sum();
+
+    let Ok(mut orthographic_projection) = camera_query.get_single_mut() else {
+        return;
+    };
+    let Some((level_origin, level_size, level_iid)) =
+        current_level(&level_query, &ldtk_projects, &ldtk_project_assets)
+    else {
+        return;
+    };
+    let (window_aspect_ratio, window_height) = window
+        .get_single()
+        .map(|w| (w.width() / w.height(), w.height()))
+        .unwrap_or((1., 1.));
+
+    let view = fit_view(level_size, window_aspect_ratio);
+    orthographic_projection.viewport_origin = Vec2::splat(0.5);
+    orthographic_projection.scaling_mode = ScalingMode::Fixed {
+        width: view.x,
+        height: view.y,
+    };
+
+    // start on the player at the play mode zoom when entering edit mode or another room
+    if controller.editing.as_ref() != Some(&level_iid) {
+        controller.editing = Some(level_iid);
+        controller.zoom = 1.;
+        controller.position = player_query
+            .get_single()
+            .map(|transform| transform.translation.truncate())
+            .unwrap_or(level_origin + level_size / 2.);
+    }
+    // play mode snaps back to the player wherever the edit camera was left
+    controller.tracking = None;
+    controller.velocity = Vec2::ZERO;
+
+    let fit_zoom = (level_size / view).max_element();
+    if keys.just_pressed(KeyCode::F) {
+        controller.fit_requested = true;
+    }
+    if controller.fit_requested {
+        controller.fit_requested = false;
+        controller.zoom = fit_zoom;
+        controller.position = level_origin + level_size / 2.;
+    }
+
+    let max_zoom = controller.max_zoom.max(fit_zoom);
+    controller.zoom = (controller.zoom * 0.9_f32.powf(scroll)).clamp(controller.min_zoom, max_zoom);
+
+    let world_per_pixel = view.y * controller.zoom / window_height;
+    if mouse_buttons.pressed(MouseButton::Middle) {
+        controller.position += Vec2::new(-drag.x, drag.y) * world_per_pixel;
+    }
+
+    let mut direction = Vec2::ZERO;
+    if keys.any_pressed([KeyCode::A, KeyCode::Left]) {
+        direction.x -= 1.;
+    }
+    if keys.any_pressed([KeyCode::D, KeyCode::Right]) {
+        direction.x += 1.;
+    }
+    if keys.any_pressed([KeyCode::S, KeyCode::Down]) {
+        direction.y -= 1.;
+    }
+    if keys.any_pressed([KeyCode::W, KeyCode::Up]) {
+        direction.y += 1.;
+    }
+    let speed = controller.pan_speed * controller.zoom;
+    controller.position += direction.normalize_or_zero() * speed * time.delta_seconds();
+
+    // picking goes through `Camera::viewport_to_world_2d`, which already accounts for the scale
+    orthographic_projection.scale = controller.zoom;
+    controller.position = controller
+        .position
+        .clamp(level_origin, level_origin + level_size);
+}
+
+fn add_trauma(mut audio_events: EventReader<AudioEvent>, mut controller: ResMut<CameraController>) {
+    for event in audio_events.read() {
+        match event.name() {
+            "fall" => controller.add_trauma(0.6),
+            "eagle" => controller.add_trauma(0.8),
+            _ => (),
+        }
+    }
+}
+
+fn shake(
+    mut camera_query: Query<&mut Transform, With<OrthographicProjection>>,
+    mut controller: ResMut<CameraController>,
+    time: Res<Time>,
+) {
+    let Ok(mut camera_transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    // shake grows with the square of the trauma so that small hits stay subtle
+    let t = time.elapsed_seconds();
+    let shake = controller.trauma * controller.trauma;
+    let noise = Vec2::new(
+        (t * 47.).sin() * (t * 13.).cos(),
+        (t * 41.).cos() * (t * 17.).sin(),
+    );
+    let mut translation = controller.position + controller.max_shake * shake * noise;
+
+    if let Some(snap) = controller.pixel_snap {
+        translation = (translation / snap).round() * snap;
+    }
+    camera_transform.translation.x = translation.x;
+    camera_transform.translation.y = translation.y;
+
+    if controller.trauma > 0. {
+        let decay = controller.trauma_decay * time.delta_seconds();
+        controller.trauma = (controller.trauma - decay).max(0.);
+    }
+}
@@ -1,10 +1,36 @@
 use bevy::prelude::*;
 use bevy_ecs_ldtk::{prelude::*, utils::ldtk_pixel_coords_to_translation_pivoted};
 
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
+
+use crate::animation::Animation;
 
 use bevy_rapier2d::prelude::*;
 
+/// Spawns the game's entities and tiles from the LDtk levels.
+pub struct ComponentsPlugin;
+
+impl Plugin for ComponentsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_int_cell::<WallBundle>(1)
+            .register_ldtk_int_cell::<LadderBundle>(2)
+            .register_ldtk_int_cell::<WallBundle>(3)
+            .register_ldtk_int_cell::<HazardBundle>(4)
+            .register_ldtk_int_cell::<HazardBundle>(5)
+            .register_ldtk_int_cell::<HazardBundle>(6)
+            .register_ldtk_entity::<PlayerBundle>("Player")
+            .register_ldtk_entity::<MobBundle>("Mob")
+            .register_ldtk_entity::<ChestBundle>("Chest")
+            .register_ldtk_entity::<MovingPlatformBundle>("MovingPlatform")
+            .register_ldtk_entity::<PropBundle>("Crate")
+            .register_ldtk_entity::<PropBundle>("Ball")
+            .register_ldtk_entity::<PropBundle>("Plank")
+            .register_ldtk_entity::<ExitBundle>("Exit")
+            .register_ldtk_entity::<EntranceBundle>("Entrance")
+            .register_ldtk_entity::<PumpkinsBundle>("Pumpkins");
+    }
+}
+
 #[derive(Clone, Default, Bundle, LdtkIntCell)]
 pub struct ColliderBundle {
     pub collider: Collider,
@@ -13,6 +39,7 @@ pub struct ColliderBundle {
     pub rotation_constraints: LockedAxes,
     pub gravity_scale: GravityScale,
     pub friction: Friction,
+    pub restitution: Restitution,
     pub density: ColliderMassProperties,
     pub active_events: ActiveEvents,
 }
@@ -42,11 +69,21 @@ impl From<&EntityInstance> for ColliderBundle {
             },
             "Mob" => ColliderBundle {
                 collider: Collider::cuboid(5., 5.),
-                rigid_body: RigidBody::KinematicVelocityBased,
+                rigid_body: RigidBody::KinematicPositionBased,
                 rotation_constraints,
                 active_events: ActiveEvents::COLLISION_EVENTS,
                 ..Default::default()
             },
+            "MovingPlatform" => ColliderBundle {
+                collider: Collider::cuboid(
+                    entity_instance.width as f32 / 2.,
+                    entity_instance.height as f32 / 2.,
+                ),
+                rigid_body: RigidBody::KinematicPositionBased,
+                friction: Friction::new(1.0),
+                rotation_constraints,
+                ..Default::default()
+            },
             "Chest" => ColliderBundle {
                 collider: Collider::cuboid(12., 12.),
                 rigid_body: RigidBody::Dynamic,
@@ -57,6 +94,43 @@ impl From<&EntityInstance> for ColliderBundle {
                 active_events: ActiveEvents::COLLISION_EVENTS,
                 ..Default::default()
             },
+            "Crate" | "Ball" | "Plank" => {
+                let float_field = |identifier: &str, default: f32| {
+                    entity_instance
+                        .get_float_field(identifier)
+                        .copied()
+                        .unwrap_or(default)
+                };
+                let half_width = entity_instance.width as f32 / 2.;
+                let half_height = entity_instance.height as f32 / 2.;
+
+                ColliderBundle {
+                    collider: if entity_instance.identifier == "Ball" {
+                        Collider::ball(half_width.min(half_height))
+                    } else {
+                        Collider::cuboid(half_width, half_height)
+                    },
+                    rigid_body: RigidBody::Dynamic,
+                    rotation_constraints: if entity_instance
+                        .get_bool_field("lock_rotation")
+                        .copied()
+                        .unwrap_or(false)
+                    {
+                        rotation_constraints
+                    } else {
+                        LockedAxes::empty()
+                    },
+                    gravity_scale: GravityScale(1.0),
+                    friction: Friction::new(float_field("friction", 1.0)),
+                    restitution: Restitution::coefficient(float_field("restitution", 0.0)),
+                    // as heavy as the chest unless the level says otherwise
+                    density: match entity_instance.get_float_field("mass") {
+                        Ok(mass) => ColliderMassProperties::Mass(*mass),
+                        Err(_) => ColliderMassProperties::Density(150.0),
+                    },
+                    ..Default::default()
+                }
+            }
             _ => ColliderBundle::default(),
         }
     }
@@ -66,16 +140,36 @@ impl From<IntGridCell> for SensorBundle {
     fn from(int_grid_cell: IntGridCell) -> SensorBundle {
         let rotation_constraints = LockedAxes::ROTATION_LOCKED;
 
-        // ladder
-        if int_grid_cell.value == 2 {
-            SensorBundle {
+        match int_grid_cell.value {
+            // ladder, water, lava
+            2 | 5 | 6 => SensorBundle {
                 collider: Collider::cuboid(8., 8.),
                 sensor: Sensor,
                 rotation_constraints,
                 active_events: ActiveEvents::COLLISION_EVENTS,
-            }
-        } else {
-            SensorBundle::default()
+            },
+            // spikes, a bit smaller than the tile to be forgiving
+            4 => SensorBundle {
+                collider: Collider::cuboid(6., 6.),
+                sensor: Sensor,
+                rotation_constraints,
+                active_events: ActiveEvents::COLLISION_EVENTS,
+            },
+            _ => SensorBundle::default(),
+        }
+    }
+}
+
+impl From<&EntityInstance> for SensorBundle {
+    fn from(entity_instance: &EntityInstance) -> SensorBundle {
+        SensorBundle {
+            collider: Collider::cuboid(
+                entity_instance.width as f32 / 2.,
+                entity_instance.height as f32 / 2.,
+            ),
+            sensor: Sensor,
+            rotation_constraints: LockedAxes::ROTATION_LOCKED,
+            active_events: ActiveEvents::COLLISION_EVENTS,
         }
     }
 }
@@ -114,7 +208,10 @@ pub struct PlayerBundle {
     #[worldly]
     pub worldly: Worldly,
     pub climber: Climber,
+    pub swimmer: Swimmer,
     pub ground_detection: GroundDetection,
+    #[from_entity_instance]
+    pub animation: Animation,
 
     // Build Items Component manually by using `impl From<&EntityInstance>`
     #[from_entity_instance]
@@ -145,14 +242,143 @@ pub struct LadderBundle {
     pub climbable: Climbable,
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub enum Hazard {
+    #[default]
+    Spikes,
+    Water,
+    Lava,
+}
+
+impl From<IntGridCell> for Hazard {
+    fn from(int_grid_cell: IntGridCell) -> Hazard {
+        match int_grid_cell.value {
+            5 => Hazard::Water,
+            6 => Hazard::Lava,
+            _ => Hazard::Spikes,
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkIntCell)]
+pub struct HazardBundle {
+    #[from_int_grid_cell]
+    pub sensor_bundle: SensorBundle,
+    #[from_int_grid_cell]
+    pub hazard: Hazard,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Component)]
+pub struct Swimmer {
+    pub intersecting_water: HashSet<Entity>,
+    pub breath: Timer,
+}
+
+impl Default for Swimmer {
+    fn default() -> Self {
+        Swimmer {
+            intersecting_water: HashSet::new(),
+            breath: Timer::from_seconds(8.0, TimerMode::Once),
+        }
+    }
+}
+
+impl Swimmer {
+    pub fn swimming(&self) -> bool {
+        !self.intersecting_water.is_empty()
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct Enemy;
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum PatrolMode {
+    // go back to the first point after the last one
+    Loop,
+    // turn around at both ends of the path
+    #[default]
+    PingPong,
+    // stop at the last point
+    Once,
+}
+
 #[derive(Clone, PartialEq, Debug, Default, Component)]
 pub struct Patrol {
     pub points: Vec<Vec2>,
     pub index: usize,
     pub forward: bool,
+    pub mode: PatrolMode,
+    pub speed: f32,
+    pub pause: Timer,
+    // where the patrol currently is on its path
+    pub position: Vec2,
+}
+
+impl Patrol {
+    /// Move along the path at `speed`, never going past a point even on long frames.
+    pub fn advance(&mut self, delta: Duration) {
+        if self.points.len() <= 1 || self.is_done() || !self.pause.tick(delta).finished() {
+            return;
+        }
+
+        let mut remaining = self.speed * delta.as_secs_f32();
+        // bounded so that a path with all its points at the same place can't loop forever
+        for _ in 0..=self.points.len() {
+            if remaining <= 0. {
+                break;
+            }
+            let to_target = self.points[self.index] - self.position;
+            let distance = to_target.length();
+            if distance > remaining {
+                self.position += to_target / distance * remaining;
+                break;
+            }
+            self.position = self.points[self.index];
+            remaining -= distance;
+
+            if self.next_point() {
+                self.pause.reset();
+                if self.pause.duration() > Duration::ZERO {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Returns `true` when the point just reached is an end of the path.
+    fn next_point(&mut self) -> bool {
+        let last = self.points.len() - 1;
+        match self.mode {
+            PatrolMode::Loop => {
+                let reached = self.index;
+                self.index = (self.index + 1) % self.points.len();
+                reached == 0
+            }
+            PatrolMode::PingPong => {
+                if self.index == last {
+                    self.forward = false;
+                } else if self.index == 0 {
+                    self.forward = true;
+                }
+                let at_end = self.index == last || self.index == 0;
+                if self.forward {
+                    self.index += 1;
+                } else {
+                    self.index -= 1;
+                }
+                at_end
+            }
+            PatrolMode::Once => {
+                self.index = (self.index + 1).min(last);
+                self.position == self.points[last]
+            }
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.mode == PatrolMode::Once && self.position == self.points[self.points.len() - 1]
+    }
 }
 
 impl LdtkEntity for Patrol {
@@ -193,10 +419,85 @@ impl LdtkEntity for Patrol {
             ));
         }
 
+        let float_field = |identifier: &str, default: f32| {
+            entity_instance
+                .get_float_field(identifier)
+                .copied()
+                .unwrap_or(default)
+        };
+
+        let mode = match entity_instance
+            .get_enum_field("patrol_mode")
+            .map(String::as_str)
+        {
+            Ok("Loop") => PatrolMode::Loop,
+            Ok("Once") => PatrolMode::Once,
+            _ => PatrolMode::PingPong,
+        };
+
+        let mut pause = Timer::from_seconds(float_field("pause", 0.0), TimerMode::Once);
+        // start finished so that the patrol only waits once it reached the end of its path
+        pause.tick(pause.duration());
+
         Patrol {
+            position: points[0],
             points,
             index: 1,
             forward: true,
+            mode,
+            speed: float_field("speed", 75.0),
+            pause,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub enum MobBehaviour {
+    #[default]
+    Patrol,
+    // leave the patrol path to go after the player when they get close enough
+    Chase {
+        radius: f32,
+        chasing: bool,
+    },
+    // stop for a while at both ends of the patrol path
+    Wait {
+        pause: Duration,
+    },
+    // bob up and down while following the patrol path, the phase only moves while the game runs
+    Flying {
+        amplitude: f32,
+        phase: f32,
+    },
+}
+
+impl From<&EntityInstance> for MobBehaviour {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let float_field = |identifier: &str, default: f32| {
+            entity_instance
+                .get_float_field(identifier)
+                .copied()
+                .unwrap_or(default)
+        };
+
+        // mobs placed before the field existed keep patrolling
+        match entity_instance
+            .get_enum_field("behaviour")
+            .map(String::as_str)
+        {
+            Ok("Chase") => MobBehaviour::Chase {
+                radius: float_field("radius", 96.0),
+                chasing: false,
+            },
+            // waiting mobs pause for a second unless the level says otherwise
+            Ok("Wait") => MobBehaviour::Wait {
+                pause: Duration::from_secs_f32(float_field("pause", 1.0)),
+            },
+            Ok("Flying") => MobBehaviour::Flying {
+                amplitude: float_field("amplitude", 16.0),
+                phase: 0.0,
+            },
+            _ => MobBehaviour::Patrol,
         }
     }
 }
@@ -210,6 +511,23 @@ pub struct MobBundle {
     pub enemy: Enemy,
     #[ldtk_entity]
     pub patrol: Patrol,
+    #[from_entity_instance]
+    pub behaviour: MobBehaviour,
+    #[from_entity_instance]
+    pub animation: Animation,
+}
+
+#[derive(Clone, Debug, Component)]
+pub struct Defeated {
+    pub timer: Timer,
+}
+
+impl Default for Defeated {
+    fn default() -> Self {
+        Defeated {
+            timer: Timer::from_seconds(0.6, TimerMode::Once),
+        }
+    }
 }
 
 #[derive(Component, Clone, Default)]
@@ -222,6 +540,54 @@ pub struct ChestBundle {
     #[from_entity_instance]
     pub collider_bundle: ColliderBundle,
     pub chest: Chest,
+    pub ground_detection: GroundDetection,
+    #[from_entity_instance]
+    pub prop: Prop,
+}
+
+// A dynamic body of the level. Losing a required one below the level ends the playthrough.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Prop {
+    pub name: String,
+    pub required: bool,
+}
+
+impl From<&EntityInstance> for Prop {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        Prop {
+            name: entity_instance.identifier.clone(),
+            // no pancakes, no win
+            required: entity_instance.identifier == "Chest"
+                || entity_instance
+                    .get_bool_field("required")
+                    .copied()
+                    .unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct PropBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    #[from_entity_instance]
+    pub prop: Prop,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct MovingPlatform;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct MovingPlatformBundle {
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    pub collider_bundle: ColliderBundle,
+    pub moving_platform: MovingPlatform,
+    #[ldtk_entity]
+    pub patrol: Patrol,
 }
 
 #[derive(Clone, Default, Bundle, LdtkEntity)]
@@ -230,6 +596,41 @@ pub struct PumpkinsBundle {
     pub sprite_sheet_bundle: SpriteSheetBundle,
 }
 
+// Leads to an `Entrance` of another room of the same stage
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Exit {
+    pub level_iid: String,
+    pub entrance_iid: String,
+}
+
+impl From<&EntityInstance> for Exit {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let to = entity_instance
+            .get_entity_ref_field("to")
+            .expect("to field should be correctly typed");
+        Exit {
+            level_iid: to.level_iid.clone(),
+            entrance_iid: to.entity_iid.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct ExitBundle {
+    #[from_entity_instance]
+    pub sensor_bundle: SensorBundle,
+    #[from_entity_instance]
+    pub exit: Exit,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Entrance;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct EntranceBundle {
+    pub entrance: Entrance,
+}
+
 #[derive(Clone, Default, Component)]
 pub struct GroundDetection {
     pub on_ground: bool,
@@ -2,8 +2,8 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::assets::LdtkProject;
 
 use crate::{
-    audio::AudioEvent, FontHandle, GameKind, GameMode, HOVERED_BUTTON, NORMAL_BUTTON,
-    PRESSED_BUTTON, TEXT_COLOR,
+    audio::AudioEvent, touch::TouchControls, FontHandle, GameKind, GameMode, PauseState,
+    HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
 };
 
 pub struct CrashPlugin;
@@ -14,6 +14,7 @@ impl Plugin for CrashPlugin {
             Update,
             crash
                 .run_if(in_state(GameMode::Play))
+                .run_if(in_state(PauseState::Running))
                 .run_if(in_state(GameKind::Platformer)),
         )
         .add_systems(OnEnter(GameMode::Crash), setup)
@@ -24,15 +25,17 @@ impl Plugin for CrashPlugin {
 
 fn crash(
     input: Res<Input<KeyCode>>,
+    touch: Res<TouchControls>,
     time: Res<Time>,
     mut acc: Local<f32>,
     mut next_state: ResMut<NextState<GameMode>>,
     mut next_kind: ResMut<NextState<GameKind>>,
 ) {
-    if input.pressed(KeyCode::A) || input.pressed(KeyCode::D) {
+    // on-screen controls only count once per frame however many fingers are down
+    if input.pressed(KeyCode::A) || input.pressed(KeyCode::D) || touch.left() || touch.right() {
         *acc += time.delta_seconds();
     }
-    if input.just_pressed(KeyCode::Space) {
+    if input.just_pressed(KeyCode::Space) || touch.jump_just_pressed {
         *acc += 0.5;
     }
     if *acc > 1.0 {
@@ -56,7 +59,7 @@ fn setup(
     world_query: Query<Entity, With<Handle<LdtkProject>>>,
     mut audio_events: EventWriter<AudioEvent>,
 ) {
-    audio_events.send(AudioEvent::Crash);
+    audio_events.send(AudioEvent::new("crash"));
     commands.entity(world_query.single()).despawn_recursive();
     commands.insert_resource(ClearColor(Color::BLUE));
 
@@ -184,7 +187,7 @@ fn button_system(
     for (interaction, mut color, button) in &mut interaction_query {
         *color = match *interaction {
             Interaction::Pressed => {
-                audio_events.send(AudioEvent::Click);
+                audio_events.send(AudioEvent::new("click"));
                 match button {
                     ButtonAction::Menu => next_state.set(GameMode::Menu),
                 }
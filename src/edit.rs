@@ -1,16 +1,27 @@
-use bevy::{math::Vec3A, prelude::*, render::primitives::Aabb, utils::HashSet};
-use bevy_ecs_ldtk::{assets::LdtkProject, GridCoords, Respawn};
+use bevy::{
+    math::Vec3A,
+    prelude::*,
+    render::primitives::Aabb,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_ldtk::{assets::LdtkProject, GridCoords, LevelIid, LevelSelection, Respawn};
 use bevy_mod_picking::{
     events::{Click, Out, Over, Pointer},
+    pointer::PointerButton,
     prelude::On,
     PickableBundle,
 };
 use bevy_rapier2d::plugin::RapierConfiguration;
-use rand::seq::IteratorRandom;
+use rand::seq::SliceRandom;
 
 use crate::{
-    audio::AudioEvent, components::Wall, CurrentLevel, FontHandle, GameMode, HOVERED_BUTTON,
-    LEVELS, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
+    audio::{AudioEvent, Emitter},
+    camera::CameraController,
+    components::Wall,
+    particles::{ParticleEvent, ParticleKind},
+    rooms::{next_room, stage_rooms, start_room},
+    CurrentLevel, FontHandle, GameMode, LdtkHandle, LevelInfo, HOVERED_BUTTON, LEVELS,
+    NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
 };
 
 pub struct EditPlugin;
@@ -24,7 +35,6 @@ impl Plugin for EditPlugin {
                 set_color_based_on_enabled,
                 update_collider_count,
                 button_system,
-                crate::play::camera_fit_inside_current_level,
             )
                 .run_if(in_state(GameMode::Edit)),
         )
@@ -42,6 +52,7 @@ fn exit_mode(mut commands: Commands, query: Query<Entity, With<OnEditMode>>) {
 #[derive(Component)]
 struct OnEditMode;
 
+#[allow(clippy::too_many_arguments)]
 fn setup_edit_mode(
     mut commands: Commands,
     world_query: Query<Entity, With<Handle<LdtkProject>>>,
@@ -49,6 +60,8 @@ fn setup_edit_mode(
     colliders: Res<EnabledColliders>,
     font: Res<FontHandle>,
     level: Res<CurrentLevel>,
+    world: Res<LdtkHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
 ) {
     let button_style = Style {
         width: Val::Px(150.0),
@@ -68,8 +81,16 @@ fn setup_edit_mode(
     for world_entity in &world_query {
         commands.entity(world_entity).insert(Respawn);
     }
+    commands.insert_resource(LevelSelection::index(level.0));
     rapier_config.gravity = Vec2::new(0.0, 0.0);
 
+    let rooms = ldtk_project_assets
+        .get(&world.0)
+        .and_then(|ldtk_project| {
+            start_room(ldtk_project, level.0).map(|start| stage_rooms(ldtk_project, &start))
+        })
+        .map_or(1, |rooms| rooms.len());
+
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -101,7 +122,7 @@ fn setup_edit_mode(
             parent.spawn(
                 TextBundle::from_sections([
                     TextSection {
-                        value: colliders.coords.len().to_string(),
+                        value: colliders.count().to_string(),
                         style: TextStyle {
                             font_size: 20.,
                             color: TEXT_COLOR,
@@ -152,6 +173,40 @@ fn setup_edit_mode(
                         button_text_style.clone(),
                     ));
                 });
+            if rooms > 1 {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: button_style.clone(),
+                            background_color: NORMAL_BUTTON.into(),
+                            border_color: BorderColor(HOVERED_BUTTON),
+                            ..default()
+                        },
+                        ButtonAction::NextRoom,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            "Next room",
+                            button_text_style.clone(),
+                        ));
+                    });
+            }
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: button_style.clone(),
+                        background_color: NORMAL_BUTTON.into(),
+                        border_color: BorderColor(HOVERED_BUTTON),
+                        ..default()
+                    },
+                    ButtonAction::FitLevel,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Fit level",
+                        button_text_style.clone(),
+                    ));
+                });
         });
 }
 
@@ -160,31 +215,107 @@ enum ButtonAction {
     Play,
     Reset,
     Random,
+    NextRoom,
+    FitLevel,
 }
 
 #[derive(Component)]
 struct ColliderStatus {
     enabled: bool,
+    level_iid: String,
 }
 
 #[derive(Resource, Default)]
 pub struct EnabledColliders {
-    pub coords: HashSet<GridCoords>,
+    // enabled walls by IID of their room, a stage can span several rooms
+    pub coords: HashMap<String, HashSet<GridCoords>>,
+    // moving platforms can't be toggled but still use up the collider budget
+    pub platforms: usize,
+}
+
+impl EnabledColliders {
+    pub fn count(&self) -> usize {
+        self.coords.values().map(HashSet::len).sum::<usize>() + self.platforms
+    }
+
+    pub fn contains(&self, level_iid: &str, coords: &GridCoords) -> bool {
+        self.coords
+            .get(level_iid)
+            .is_some_and(|room| room.contains(coords))
+    }
+
+    pub fn reset(&mut self, start_room: Option<String>, level: &LevelInfo) {
+        self.coords.clear();
+        if let Some(start_room) = start_room {
+            self.coords
+                .insert(start_room, level.start_colliders.iter().copied().collect());
+        }
+    }
+
+    /// Text form of the enabled walls, like `room-iid:3.4,5.4|other-iid:1.2`, sorted so the
+    /// same layout always gives the same code.
+    pub fn solution_code(&self) -> String {
+        let mut rooms: Vec<_> = self
+            .coords
+            .iter()
+            .filter(|(_, coords)| !coords.is_empty())
+            .map(|(iid, coords)| {
+                let mut coords: Vec<_> = coords.iter().map(|c| (c.x, c.y)).collect();
+                coords.sort_unstable();
+                let coords: Vec<_> = coords.iter().map(|(x, y)| format!("{x}.{y}")).collect();
+                format!("{}:{}", iid, coords.join(","))
+            })
+            .collect();
+        rooms.sort_unstable();
+        rooms.join("|")
+    }
+
+    /// Reads back a layout from [`EnabledColliders::solution_code`], platforms are counted
+    /// again when the stage is loaded.
+    pub fn from_solution_code(code: &str) -> Option<Self> {
+        let mut coords = HashMap::default();
+        for room in code.split('|').filter(|room| !room.is_empty()) {
+            let (iid, walls) = room.split_once(':')?;
+            let walls = walls
+                .split(',')
+                .map(|wall| {
+                    let (x, y) = wall.split_once('.')?;
+                    Some(GridCoords::new(x.parse().ok()?, y.parse().ok()?))
+                })
+                .collect::<Option<HashSet<_>>>()?;
+            coords.insert(iid.to_string(), walls);
+        }
+        Some(EnabledColliders {
+            coords,
+            platforms: 0,
+        })
+    }
 }
 
 fn spawn_wall_aabb(
     mut commands: Commands,
-    wall_query: Query<(Entity, &GridCoords), Added<Wall>>,
+    wall_query: Query<(Entity, &GridCoords, &Parent), Added<Wall>>,
+    parent_query: Query<&Parent, Without<Wall>>,
+    level_query: Query<&LevelIid>,
     enabled: Res<EnabledColliders>,
 ) {
-    wall_query.for_each(|(entity, gridcoords)| {
+    wall_query.for_each(|(entity, gridcoords, parent)| {
+        let Some(level_iid) = parent_query
+            .get(parent.get())
+            .and_then(|grandparent| level_query.get(grandparent.get()))
+            .ok()
+            .map(|level_iid| level_iid.to_string())
+        else {
+            return;
+        };
+        let is_enabled = enabled.contains(&level_iid, gridcoords);
         commands.entity(entity).insert((
             Aabb {
                 center: Vec3A::ZERO,
                 half_extents: Vec3A::new(8., 8., 0.) * 0.95,
             },
             AabbGizmo {
-                color: Some(if enabled.coords.contains(gridcoords) {
+                color: Some(if is_enabled {
                     Color::GREEN
                 } else {
                     Color::GRAY
@@ -192,7 +323,8 @@ fn spawn_wall_aabb(
             },
             PickableBundle::default(),
             ColliderStatus {
-                enabled: enabled.coords.contains(gridcoords),
+                enabled: is_enabled,
+                level_iid,
             },
             On::<Pointer<Out>>::target_component_mut::<AabbGizmo>(|_, gizmo| {
                 let color = gizmo.color.unwrap();
@@ -206,35 +338,53 @@ fn spawn_wall_aabb(
                 color.set_l(0.9);
                 gizmo.color = Some(color.as_rgba());
             }),
-            On::<Pointer<Click>>::target_component_mut::<ColliderStatus>(|_, collider| {
-                collider.enabled = !collider.enabled;
+            // the middle button pans the camera
+            On::<Pointer<Click>>::target_component_mut::<ColliderStatus>(|click, collider| {
+                if click.button == PointerButton::Primary {
+                    collider.enabled = !collider.enabled;
+                }
             }),
         ));
     });
 }
 
 fn set_color_based_on_enabled(
-    mut query: Query<(&mut ColliderStatus, &mut AabbGizmo, &GridCoords)>,
+    mut query: Query<(Entity, &mut ColliderStatus, &mut AabbGizmo, &GridCoords)>,
     mut enabled: ResMut<EnabledColliders>,
     level: Res<CurrentLevel>,
     mut audio_events: EventWriter<AudioEvent>,
+    mut particle_events: EventWriter<ParticleEvent>,
 ) {
-    for (mut collider_status, mut gizmo, gridcoords) in &mut query {
+    for (entity, mut collider_status, mut gizmo, gridcoords) in &mut query {
         if collider_status.is_changed() && !collider_status.is_added() {
             if collider_status.enabled {
-                if enabled.coords.len() >= LEVELS[level.0].max_colliders {
+                if enabled.count() >= LEVELS[level.0].max_colliders {
                     collider_status.enabled = false;
-                    audio_events.send(AudioEvent::FailedCollider);
+                    audio_events.send(AudioEvent::new("failed_collider"));
+                    particle_events.send(ParticleEvent::new(
+                        ParticleKind::FailFlash,
+                        Emitter::Entity(entity),
+                    ));
                 } else {
                     debug!("{:?}", gridcoords);
                     gizmo.color = Some(Color::GREEN);
-                    enabled.coords.insert(*gridcoords);
-                    audio_events.send(AudioEvent::AddCollider);
+                    enabled
+                        .coords
+                        .entry(collider_status.level_iid.clone())
+                        .or_default()
+                        .insert(*gridcoords);
+                    audio_events.send(AudioEvent::new("add_collider"));
+                    particle_events.send(ParticleEvent::new(
+                        ParticleKind::Sparkle,
+                        Emitter::Entity(entity),
+                    ));
                 }
             } else {
                 gizmo.color = Some(Color::GRAY);
-                enabled.coords.remove(gridcoords);
-                audio_events.send(AudioEvent::RemoveCollider);
+                if let Some(room) = enabled.coords.get_mut(&collider_status.level_iid) {
+                    room.remove(gridcoords);
+                }
+                audio_events.send(AudioEvent::new("remove_collider"));
             }
         }
     }
@@ -244,7 +394,7 @@ fn update_collider_count(colliders: Res<EnabledColliders>, mut text: Query<&mut
     if colliders.is_changed() {
         for mut text in &mut text {
             if text.sections.len() == 2 {
-                text.sections[0].value = colliders.coords.len().to_string();
+                text.sections[0].value = colliders.count().to_string();
             }
         }
     }
@@ -260,41 +410,66 @@ fn button_system(
     mut next_state: ResMut<NextState<GameMode>>,
     mut enabled: ResMut<EnabledColliders>,
     world_query: Query<Entity, With<Handle<LdtkProject>>>,
-    wall_query: Query<&GridCoords, With<Wall>>,
+    wall_query: Query<(&GridCoords, &ColliderStatus), With<Wall>>,
     level: Res<CurrentLevel>,
+    mut level_selection: ResMut<LevelSelection>,
+    world: Res<LdtkHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut camera_controller: ResMut<CameraController>,
     mut audio_events: EventWriter<AudioEvent>,
 ) {
+    let ldtk_project = ldtk_project_assets.get(&world.0);
+    let start = ldtk_project.and_then(|ldtk_project| start_room(ldtk_project, level.0));
+
     for (interaction, mut color, button) in &mut interaction_query {
         *color = match *interaction {
             Interaction::Pressed => {
-                audio_events.send(AudioEvent::Click);
+                audio_events.send(AudioEvent::new("click"));
                 match button {
                     ButtonAction::Play => next_state.set(GameMode::Play),
                     ButtonAction::Reset => {
-                        enabled.coords.clear();
-                        for coord in &LEVELS[level.0].start_colliders {
-                            enabled.coords.insert(*coord);
-                        }
+                        enabled.reset(start.clone(), &LEVELS[level.0]);
+                        *level_selection = LevelSelection::index(level.0);
                         for world_entity in &world_query {
                             commands.entity(world_entity).insert(Respawn);
                         }
                     }
+                    // the room hasn't spawned yet, there is nothing to pick from
+                    ButtonAction::Random if wall_query.is_empty() => {}
                     ButtonAction::Random => {
-                        enabled.coords.clear();
-                        for coord in &LEVELS[level.0].start_colliders {
-                            enabled.coords.insert(*coord);
-                        }
+                        enabled.reset(start.clone(), &LEVELS[level.0]);
 
-                        let mut rng = rand::thread_rng();
-                        while enabled.coords.len() < LEVELS[level.0].max_colliders {
-                            let sample = wall_query.iter().choose(&mut rng).unwrap();
-                            enabled.coords.insert(*sample);
+                        // only the walls of the loaded room can be picked, and some of them
+                        // may already be enabled
+                        let remaining = LEVELS[level.0]
+                            .max_colliders
+                            .saturating_sub(enabled.count());
+                        let mut candidates: Vec<_> = wall_query
+                            .iter()
+                            .filter(|(coords, status)| !enabled.contains(&status.level_iid, coords))
+                            .collect();
+                        candidates.shuffle(&mut rand::thread_rng());
+                        for (coords, status) in candidates.into_iter().take(remaining) {
+                            enabled
+                                .coords
+                                .entry(status.level_iid.clone())
+                                .or_default()
+                                .insert(*coords);
                         }
 
+                        *level_selection = LevelSelection::index(level.0);
                         for world_entity in &world_query {
                             commands.entity(world_entity).insert(Respawn);
                         }
                     }
+                    ButtonAction::NextRoom => {
+                        if let Some(next) = ldtk_project.and_then(|ldtk_project| {
+                            next_room(ldtk_project, &level_selection, level.0)
+                        }) {
+                            *level_selection = next;
+                        }
+                    }
+                    ButtonAction::FitLevel => camera_controller.fit_level(),
                 }
                 PRESSED_BUTTON.into()
             }
@@ -0,0 +1,639 @@
+use std::cmp::Reverse;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{assets::LdtkProject, LdtkWorldBundle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::AudioEvent, edit::EnabledColliders, menu::load_level, rooms::stage_platforms,
+    settings::storage, FontHandle, GameKind, GameMode, LdtkHandle, LevelRecord, HOVERED_BUTTON,
+    LEVELS, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
+};
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Leaderboards::load())
+            .init_resource::<LeaderboardView>()
+            .add_systems(OnEnter(GameMode::Leaderboards), setup)
+            .add_systems(OnExit(GameMode::Leaderboards), exit_screen)
+            .add_systems(
+                Update,
+                (button_system, update_table)
+                    .chain()
+                    .run_if(in_state(GameMode::Leaderboards)),
+            );
+        #[cfg(feature = "online-leaderboard")]
+        app.add_plugins(crate::leaderboard_client::LeaderboardClientPlugin);
+    }
+}
+
+/// Results kept for each level.
+pub const MAX_ENTRIES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub colliders: usize,
+    pub rigid_bodies: usize,
+    pub time: f32,
+    /// Seconds since the Unix epoch.
+    pub date: u64,
+    /// Layout of the enabled colliders, see [`EnabledColliders::solution_code`].
+    pub solution: String,
+}
+
+impl LeaderboardEntry {
+    pub fn record(&self) -> LevelRecord {
+        LevelRecord {
+            colliders: self.colliders,
+            time: self.time,
+        }
+    }
+
+    // one tab separated line, names can't contain tabs or line breaks
+    fn to_line(&self, level: usize) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            level,
+            self.name.replace(['\t', '\n', '\r'], " "),
+            self.colliders,
+            self.rigid_bodies,
+            self.time,
+            self.date,
+            self.solution
+        )
+    }
+
+    fn from_line(line: &str) -> Option<(usize, Self)> {
+        let mut fields = line.split('\t');
+        let level = fields.next()?.parse().ok()?;
+        let entry = LeaderboardEntry {
+            name: fields.next()?.to_string(),
+            colliders: fields.next()?.parse().ok()?,
+            rigid_bodies: fields.next()?.parse().ok()?,
+            time: fields.next()?.parse().ok()?,
+            date: fields.next()?.parse().ok()?,
+            solution: fields.next()?.to_string(),
+        };
+        Some((level, entry))
+    }
+}
+
+/// Best local results of each level, fewest colliders first then fastest, saved on every change.
+#[derive(Resource)]
+pub struct Leaderboards {
+    pub levels: Vec<Vec<LeaderboardEntry>>,
+    /// Top lists fetched from the online leaderboard, empty without it.
+    pub online: Vec<Vec<LeaderboardEntry>>,
+}
+
+impl Leaderboards {
+    fn load() -> Self {
+        let mut leaderboards = Leaderboards {
+            levels: vec![vec![]; LEVELS.len()],
+            online: vec![vec![]; LEVELS.len()],
+        };
+        let Some(stored) = storage::read("leaderboards") else {
+            return leaderboards;
+        };
+        // bad lines and unknown levels are skipped
+        for (level, entry) in stored.lines().filter_map(LeaderboardEntry::from_line) {
+            if let Some(entries) = leaderboards.levels.get_mut(level) {
+                entries.push(entry);
+            }
+        }
+        for entries in &mut leaderboards.levels {
+            entries.sort_by(|a, b| {
+                a.colliders
+                    .cmp(&b.colliders)
+                    .then(a.time.total_cmp(&b.time))
+            });
+            entries.truncate(MAX_ENTRIES);
+        }
+        leaderboards
+    }
+
+    fn save(&self) {
+        let mut contents = String::new();
+        for (level, entries) in self.levels.iter().enumerate() {
+            for entry in entries {
+                contents.push_str(&entry.to_line(level));
+                contents.push('\n');
+            }
+        }
+        storage::write("leaderboards", &contents);
+    }
+
+    /// Adds a result to a level and returns its place, or `None` if it didn't make the list.
+    pub fn submit(&mut self, level: usize, entry: LeaderboardEntry) -> Option<usize> {
+        let place = insert_ranked(&mut self.levels[level], entry);
+        if place.is_some() {
+            self.save();
+        }
+        place
+    }
+}
+
+/// Inserts a result in a ranked list kept to [`MAX_ENTRIES`] and returns its place.
+pub fn insert_ranked(
+    entries: &mut Vec<LeaderboardEntry>,
+    entry: LeaderboardEntry,
+) -> Option<usize> {
+    // ties go after the results that were there first
+    let place = entries
+        .iter()
+        .position(|other| entry.record().beats(&other.record()))
+        .unwrap_or(entries.len());
+    if place >= MAX_ENTRIES {
+        return None;
+    }
+    entries.insert(place, entry);
+    entries.truncate(MAX_ENTRIES);
+    Some(place)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// the system clock isn't available on the web
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> u64 {
+    (js_sys::Date::now() / 1000.) as u64
+}
+
+// `YYYY-MM-DD` in UTC, from the days since the epoch to the proleptic Gregorian calendar
+fn format_date(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortBy {
+    Colliders,
+    RigidBodies,
+    Time,
+    Date,
+    Name,
+}
+
+impl SortBy {
+    fn name(&self) -> &'static str {
+        match self {
+            SortBy::Colliders => "Colliders",
+            SortBy::RigidBodies => "Bodies",
+            SortBy::Time => "Time",
+            SortBy::Date => "Date",
+            SortBy::Name => "Name",
+        }
+    }
+}
+
+// Level and order shown on the screen, kept when coming back to it.
+#[derive(Resource)]
+struct LeaderboardView {
+    level: usize,
+    sort: SortBy,
+    online: bool,
+}
+
+impl Default for LeaderboardView {
+    fn default() -> Self {
+        LeaderboardView {
+            level: 0,
+            sort: SortBy::Colliders,
+            online: false,
+        }
+    }
+}
+
+#[derive(Component)]
+struct OnLeaderboardsScreen;
+
+#[derive(Component)]
+struct LeaderboardTable;
+
+#[derive(Component)]
+struct ViewTitle;
+
+// widths of the place, name, colliders, rigid bodies, time, date and load columns
+const COLUMNS: [f32; 7] = [50.0, 220.0, 150.0, 110.0, 110.0, 160.0, 100.0];
+
+fn exit_screen(mut commands: Commands, query: Query<Entity, With<OnLeaderboardsScreen>>) {
+    for entity in &mut query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    font: Res<FontHandle>,
+    mut view: ResMut<LeaderboardView>,
+    #[cfg(feature = "online-leaderboard")] client: Option<
+        Res<crate::leaderboard_client::LeaderboardClient>,
+    >,
+) {
+    // fills the table on the first frame
+    view.set_changed();
+    // the online lists only exist with a server in the settings
+    #[cfg(feature = "online-leaderboard")]
+    let online = client.is_some();
+    #[cfg(not(feature = "online-leaderboard"))]
+    let online = false;
+    if !online {
+        view.online = false;
+    }
+
+    let button_style = Style {
+        height: Val::Px(40.0),
+        margin: UiRect::all(Val::Px(4.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(3.0)),
+        ..default()
+    };
+    let text_style = TextStyle {
+        font_size: 20.0,
+        color: TEXT_COLOR,
+        font: font.0.clone(),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::rgba(0.3, 0.3, 0.3, 0.5).into(),
+                ..default()
+            },
+            OnLeaderboardsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 50.0,
+                        color: TEXT_COLOR,
+                        font: font.0.clone(),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(30.0)),
+                    ..default()
+                }),
+                ViewTitle,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        margin: UiRect::bottom(Val::Px(20.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for level in 0..LEVELS.len() {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(60.0),
+                                        ..button_style.clone()
+                                    },
+                                    background_color: NORMAL_BUTTON.into(),
+                                    border_color: BorderColor(HOVERED_BUTTON),
+                                    ..default()
+                                },
+                                ButtonAction::Level(level),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    format!("{}", level + 1),
+                                    text_style.clone(),
+                                ));
+                            });
+                    }
+                    if online {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(200.0),
+                                        margin: UiRect::left(Val::Px(30.0)),
+                                        ..button_style.clone()
+                                    },
+                                    background_color: NORMAL_BUTTON.into(),
+                                    border_color: BorderColor(HOVERED_BUTTON),
+                                    ..default()
+                                },
+                                ButtonAction::ToggleOnline,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    "Local/Online",
+                                    text_style.clone(),
+                                ));
+                            });
+                    }
+                });
+
+            // the column headers sort the table
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section("#", text_style.clone()).with_style(Style {
+                            width: Val::Px(COLUMNS[0]),
+                            ..default()
+                        }),
+                    );
+                    for (sort, width) in [
+                        SortBy::Name,
+                        SortBy::Colliders,
+                        SortBy::RigidBodies,
+                        SortBy::Time,
+                        SortBy::Date,
+                    ]
+                    .into_iter()
+                    .zip(&COLUMNS[1..6])
+                    {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(width - 8.0),
+                                        ..button_style.clone()
+                                    },
+                                    background_color: NORMAL_BUTTON.into(),
+                                    border_color: BorderColor(HOVERED_BUTTON),
+                                    ..default()
+                                },
+                                ButtonAction::Sort(sort),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    sort.name(),
+                                    text_style.clone(),
+                                ));
+                            });
+                    }
+                    parent.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(COLUMNS[6]),
+                            ..default()
+                        },
+                        ..default()
+                    });
+                });
+
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        min_height: Val::Px(420.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                LeaderboardTable,
+            ));
+
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(250.0),
+                            height: Val::Px(60.0),
+                            margin: UiRect::all(Val::Px(30.0)),
+                            ..button_style.clone()
+                        },
+                        background_color: NORMAL_BUTTON.into(),
+                        border_color: BorderColor(HOVERED_BUTTON),
+                        ..default()
+                    },
+                    ButtonAction::Back,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Back",
+                        TextStyle {
+                            font_size: 25.0,
+                            ..text_style.clone()
+                        },
+                    ));
+                });
+        });
+}
+
+fn update_table(
+    mut commands: Commands,
+    view: Res<LeaderboardView>,
+    leaderboards: Res<Leaderboards>,
+    font: Res<FontHandle>,
+    tables: Query<Entity, With<LeaderboardTable>>,
+    mut titles: Query<&mut Text, With<ViewTitle>>,
+) {
+    // online lists arrive while the screen is open
+    if !view.is_changed() && !leaderboards.is_changed() {
+        return;
+    }
+    for mut text in &mut titles {
+        text.sections[0].value = format!(
+            "Level {} by {}{}",
+            view.level + 1,
+            view.sort.name(),
+            if view.online { ", online" } else { "" }
+        );
+    }
+
+    let text_style = TextStyle {
+        font_size: 20.0,
+        color: TEXT_COLOR,
+        font: font.0.clone(),
+    };
+    let entries = if view.online {
+        &leaderboards.online[view.level]
+    } else {
+        &leaderboards.levels[view.level]
+    };
+    // stable sorts, so ties keep their place on the leaderboard
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    match view.sort {
+        SortBy::Colliders => (),
+        SortBy::RigidBodies => order.sort_by_key(|&i| entries[i].rigid_bodies),
+        SortBy::Time => order.sort_by(|&a, &b| entries[a].time.total_cmp(&entries[b].time)),
+        SortBy::Date => order.sort_by_key(|&i| Reverse(entries[i].date)),
+        SortBy::Name => order.sort_by_key(|&i| entries[i].name.to_lowercase()),
+    }
+
+    for table in &tables {
+        commands
+            .entity(table)
+            .despawn_descendants()
+            .with_children(|parent| {
+                if entries.is_empty() {
+                    parent.spawn(
+                        TextBundle::from_section("No results yet", text_style.clone()).with_style(
+                            Style {
+                                margin: UiRect::all(Val::Px(20.0)),
+                                align_self: AlignSelf::Center,
+                                ..default()
+                            },
+                        ),
+                    );
+                }
+                for &i in &order {
+                    let entry = &entries[i];
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                align_items: AlignItems::Center,
+                                height: Val::Px(40.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (value, width) in [
+                                format!("{}", i + 1),
+                                entry.name.clone(),
+                                format!("{}", entry.colliders),
+                                format!("{}", entry.rigid_bodies),
+                                format!("{:.1}s", entry.time),
+                                format_date(entry.date),
+                            ]
+                            .into_iter()
+                            .zip(COLUMNS)
+                            {
+                                parent.spawn(
+                                    TextBundle::from_section(value, text_style.clone()).with_style(
+                                        Style {
+                                            width: Val::Px(width),
+                                            ..default()
+                                        },
+                                    ),
+                                );
+                            }
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(COLUMNS[6]),
+                                            height: Val::Px(34.0),
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            border: UiRect::all(Val::Px(3.0)),
+                                            ..default()
+                                        },
+                                        background_color: NORMAL_BUTTON.into(),
+                                        border_color: BorderColor(HOVERED_BUTTON),
+                                        ..default()
+                                    },
+                                    ButtonAction::Load(view.level, entry.solution.clone()),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "Load",
+                                        text_style.clone(),
+                                    ));
+                                });
+                        });
+                }
+            });
+    }
+}
+
+#[derive(Component)]
+enum ButtonAction {
+    Level(usize),
+    Sort(SortBy),
+    // opens the editor with the colliders of an entry
+    Load(usize, String),
+    ToggleOnline,
+    Back,
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn button_system(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &ButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut next_state: ResMut<NextState<GameMode>>,
+    mut next_kind: ResMut<NextState<GameKind>>,
+    mut view: ResMut<LeaderboardView>,
+    world: Res<LdtkHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut audio_events: EventWriter<AudioEvent>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        *color = match *interaction {
+            Interaction::Pressed => {
+                audio_events.send(AudioEvent::new("click"));
+                match button {
+                    ButtonAction::Level(level) => view.level = *level,
+                    ButtonAction::Sort(sort) => view.sort = *sort,
+                    ButtonAction::Load(level, solution) => {
+                        let Some(mut colliders) = EnabledColliders::from_solution_code(solution)
+                        else {
+                            continue;
+                        };
+                        let ldtk_project = ldtk_project_assets.get(&world.0);
+                        load_level(&mut commands, ldtk_project, *level, &GameKind::Puzzle);
+                        colliders.platforms = ldtk_project
+                            .map_or(0, |ldtk_project| stage_platforms(ldtk_project, *level));
+                        commands.insert_resource(colliders);
+                        commands.spawn(LdtkWorldBundle {
+                            ldtk_handle: world.0.clone(),
+                            ..Default::default()
+                        });
+                        // entries are puzzle solutions, whichever game was picked in the menu
+                        next_kind.set(GameKind::Puzzle);
+                        next_state.set(GameMode::Edit);
+                    }
+                    ButtonAction::ToggleOnline => view.online = !view.online,
+                    ButtonAction::Back => next_state.set(GameMode::Menu),
+                }
+                PRESSED_BUTTON.into()
+            }
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        }
+    }
+}
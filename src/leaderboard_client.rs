@@ -0,0 +1,501 @@
+use std::{
+    io::Read,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Mutex, PoisonError,
+    },
+};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(test)]
+use crate::leaderboard::insert_ranked;
+use crate::{
+    edit::EnabledColliders,
+    leaderboard::{LeaderboardEntry, Leaderboards, MAX_ENTRIES},
+    replay::{self, Replay, ReplayPlugin},
+    settings::Settings,
+    GameMode, LEVELS,
+};
+
+pub struct LeaderboardClientPlugin;
+
+impl Plugin for LeaderboardClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ReplayPlugin)
+            .add_systems(Startup, connect)
+            .add_systems(
+                OnEnter(GameMode::Leaderboards),
+                fetch_all.run_if(resource_exists::<LeaderboardClient>()),
+            )
+            .add_systems(
+                Update,
+                receive_responses.run_if(resource_exists::<LeaderboardClient>()),
+            );
+    }
+}
+
+// Without a server in the settings there is no client, and results only go on the local lists.
+fn connect(mut commands: Commands, settings: Res<Settings>) {
+    let url = settings.leaderboard_url.trim();
+    if url.is_empty() {
+        info!("No leaderboard_url in the settings, the online leaderboard is off");
+        return;
+    }
+    commands.insert_resource(LeaderboardClient::new(Box::new(HttpBackend::new(url))));
+}
+
+/// A result sent to the online leaderboard.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Submission {
+    pub level: usize,
+    #[serde(flatten)]
+    pub entry: LeaderboardEntry,
+    /// Inputs of the run, see [`Replay::code`].
+    pub replay: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubmitResponse {
+    /// Place on the level's top list, `None` if the result didn't make it.
+    pub place: Option<usize>,
+}
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("request failed: {0}")]
+    Http(String),
+    #[error("server answered {0} {1}")]
+    Status(u16, String),
+    #[error("could not read the response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("submission rejected: {0}")]
+    Rejected(String),
+}
+
+pub type Callback<T> = Box<dyn FnOnce(Result<T, ClientError>) + Send>;
+
+/// Where results are submitted and top lists come from. Answers can come from another thread.
+pub trait LeaderboardBackend: Send + Sync {
+    fn submit(&self, submission: Submission, done: Callback<SubmitResponse>);
+    fn fetch_top(&self, level: usize, done: Callback<Vec<LeaderboardEntry>>);
+}
+
+/// Talks JSON to a leaderboard server:
+/// - `POST {url}/submissions` with a [`Submission`], answered with a [`SubmitResponse`]
+/// - `GET {url}/levels/{level}/top?limit={n}`, answered with the ranked entries
+///
+/// Levels are numbered from 0. The server is expected to check submissions with [`verify`], for
+/// example through `worst-physics-engine-ever verify < submission.json`.
+pub struct HttpBackend {
+    url: String,
+}
+
+impl HttpBackend {
+    pub fn new(url: &str) -> Self {
+        HttpBackend {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+fn fetch_json<T: DeserializeOwned + 'static>(request: ehttp::Request, done: Callback<T>) {
+    ehttp::fetch(request, move |result| {
+        done(result.map_err(ClientError::Http).and_then(|response| {
+            if !response.ok {
+                return Err(ClientError::Status(response.status, response.status_text));
+            }
+            Ok(serde_json::from_slice(&response.bytes)?)
+        }))
+    });
+}
+
+impl LeaderboardBackend for HttpBackend {
+    fn submit(&self, submission: Submission, done: Callback<SubmitResponse>) {
+        let body = match serde_json::to_vec(&submission) {
+            Ok(body) => body,
+            Err(error) => return done(Err(error.into())),
+        };
+        let mut request = ehttp::Request::post(format!("{}/submissions", self.url), body);
+        request
+            .headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        fetch_json(request, done);
+    }
+
+    fn fetch_top(&self, level: usize, done: Callback<Vec<LeaderboardEntry>>) {
+        let url = format!("{}/levels/{}/top?limit={}", self.url, level, MAX_ENTRIES);
+        fetch_json(ehttp::Request::get(url), done);
+    }
+}
+
+/// In-process stand-in for the server in tests, keeping the top lists in memory and answering
+/// right away.
+#[cfg(test)]
+pub struct MockBackend {
+    levels: Mutex<Vec<Vec<LeaderboardEntry>>>,
+    verify: fn(&Submission) -> Result<(), ClientError>,
+}
+
+#[cfg(test)]
+impl Default for MockBackend {
+    fn default() -> Self {
+        MockBackend {
+            levels: Mutex::new(vec![vec![]; LEVELS.len()]),
+            verify,
+        }
+    }
+}
+
+#[cfg(test)]
+impl MockBackend {
+    /// Accepts every submission without replaying it, to test the ranking on its own.
+    pub fn trusting() -> Self {
+        MockBackend {
+            verify: |_| Ok(()),
+            ..default()
+        }
+    }
+}
+
+#[cfg(test)]
+impl LeaderboardBackend for MockBackend {
+    fn submit(&self, submission: Submission, done: Callback<SubmitResponse>) {
+        done((self.verify)(&submission).map(|()| {
+            let mut levels = self.levels.lock().unwrap_or_else(PoisonError::into_inner);
+            SubmitResponse {
+                place: insert_ranked(&mut levels[submission.level], submission.entry),
+            }
+        }))
+    }
+
+    fn fetch_top(&self, level: usize, done: Callback<Vec<LeaderboardEntry>>) {
+        let levels = self.levels.lock().unwrap_or_else(PoisonError::into_inner);
+        done(
+            levels
+                .get(level)
+                .cloned()
+                .ok_or_else(|| ClientError::Rejected("unknown level".to_string())),
+        )
+    }
+}
+
+// frame durations are recorded to the microsecond, and the play systems aren't ordered against
+// each other, so a replay can end a little off the recorded time
+const TIME_TOLERANCE: f32 = 0.1;
+
+/// Checks a submission against the rules of its level: a readable layout within the collider
+/// budget and a time within the limit. Then replays the run headless, which has to reach the
+/// chest with the submitted colliders and time.
+pub fn verify(submission: &Submission) -> Result<(), ClientError> {
+    let reject = |reason: &str| Err(ClientError::Rejected(reason.to_string()));
+    let entry = &submission.entry;
+    let Some(info) = LEVELS.get(submission.level) else {
+        return reject("unknown level");
+    };
+    let Some(layout) = EnabledColliders::from_solution_code(&entry.solution) else {
+        return reject("unreadable solution code");
+    };
+    let Some(replay) = Replay::from_code(&submission.replay) else {
+        return reject("unreadable replay");
+    };
+    if entry.name.trim().is_empty() {
+        return reject("missing name");
+    }
+    // moving platforms aren't in the code but count towards the colliders
+    if entry.colliders < layout.count() || entry.colliders > info.max_colliders {
+        return reject("collider count doesn't match the layout");
+    }
+    if !(entry.time > 0. && entry.time <= info.time_limit) {
+        return reject("time out of the level's limit");
+    }
+
+    let run = replay::play_back(submission.level, layout, &replay)
+        .map_err(|error| ClientError::Rejected(error.to_string()))?;
+    if run.colliders != entry.colliders {
+        return reject("collider count doesn't match the layout");
+    }
+    if (run.time - entry.time).abs() > TIME_TOLERANCE {
+        return reject("time doesn't match the replay");
+    }
+    Ok(())
+}
+
+/// Verifies a [`Submission`] read as JSON from the standard input, for a server to run the game
+/// as its verifier. Exits with an error when the submission is rejected.
+pub fn verify_stdin() {
+    let mut json = String::new();
+    if let Err(error) = std::io::stdin().read_to_string(&mut json) {
+        eprintln!("Could not read the submission: {}", error);
+        std::process::exit(1);
+    }
+    let verdict = serde_json::from_str(&json)
+        .map_err(ClientError::from)
+        .and_then(|submission| verify(&submission));
+    match verdict {
+        Ok(()) => println!("accepted"),
+        Err(error) => {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+enum Response {
+    Submitted(usize, Result<SubmitResponse, ClientError>),
+    Top(usize, Result<Vec<LeaderboardEntry>, ClientError>),
+}
+
+/// Sends requests to the backend, their answers are picked up on the next frames.
+#[derive(Resource)]
+pub struct LeaderboardClient {
+    backend: Box<dyn LeaderboardBackend>,
+    sender: Sender<Response>,
+    receiver: Mutex<Receiver<Response>>,
+}
+
+impl LeaderboardClient {
+    pub fn new(backend: Box<dyn LeaderboardBackend>) -> Self {
+        let (sender, receiver) = channel();
+        LeaderboardClient {
+            backend,
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    pub fn submit(&self, level: usize, entry: LeaderboardEntry, replay: &Replay) {
+        let sender = self.sender.clone();
+        self.backend.submit(
+            Submission {
+                level,
+                entry,
+                replay: replay.code(),
+            },
+            Box::new(move |result| {
+                let _ = sender.send(Response::Submitted(level, result));
+            }),
+        );
+    }
+
+    pub fn fetch_top(&self, level: usize) {
+        let sender = self.sender.clone();
+        self.backend.fetch_top(
+            level,
+            Box::new(move |result| {
+                let _ = sender.send(Response::Top(level, result));
+            }),
+        );
+    }
+}
+
+fn fetch_all(client: Res<LeaderboardClient>) {
+    for level in 0..LEVELS.len() {
+        client.fetch_top(level);
+    }
+}
+
+fn receive_responses(client: Res<LeaderboardClient>, mut leaderboards: ResMut<Leaderboards>) {
+    let receiver = client
+        .receiver
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    for response in receiver.try_iter() {
+        match response {
+            Response::Submitted(level, Ok(response)) => {
+                if let Some(place) = response.place {
+                    info!("Placed #{} online on level {}", place + 1, level + 1);
+                }
+                client.fetch_top(level);
+            }
+            Response::Top(level, Ok(entries)) => {
+                if let Some(online) = leaderboards.online.get_mut(level) {
+                    *online = entries;
+                }
+            }
+            Response::Submitted(_, Err(error)) | Response::Top(_, Err(error)) => {
+                warn!("Online leaderboard: {}", error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::replay::ReplayError;
+
+    fn submission(colliders: usize, time: f32, solution: &str) -> Submission {
+        Submission {
+            level: 0,
+            entry: LeaderboardEntry {
+                name: "Tester".to_string(),
+                colliders,
+                rigid_bodies: 3,
+                time,
+                date: 1_700_000_000,
+                solution: solution.to_string(),
+            },
+            replay: String::new(),
+        }
+    }
+
+    fn submit(
+        backend: &MockBackend,
+        submission: Submission,
+    ) -> Result<SubmitResponse, ClientError> {
+        let (sender, receiver) = channel();
+        backend.submit(
+            submission,
+            Box::new(move |result| sender.send(result).unwrap()),
+        );
+        receiver.recv().unwrap()
+    }
+
+    fn top(backend: &MockBackend, level: usize) -> Vec<LeaderboardEntry> {
+        let (sender, receiver) = channel();
+        backend.fetch_top(level, Box::new(move |result| sender.send(result).unwrap()));
+        receiver.recv().unwrap().unwrap()
+    }
+
+    #[test]
+    fn ranks_accepted_submissions() {
+        let backend = MockBackend::trusting();
+        let place = |response: Result<SubmitResponse, ClientError>| response.unwrap().place;
+        assert_eq!(
+            place(submit(&backend, submission(4, 20., "a:1.1,2.1"))),
+            Some(0)
+        );
+        assert_eq!(
+            place(submit(&backend, submission(3, 30., "a:1.1,2.1"))),
+            Some(0)
+        );
+        assert_eq!(
+            place(submit(&backend, submission(4, 10., "a:1.1"))),
+            Some(1)
+        );
+
+        let entries = top(&backend, 0);
+        let ranked: Vec<_> = entries.iter().map(|e| (e.colliders, e.time)).collect();
+        assert_eq!(ranked, [(3, 30.), (4, 10.), (4, 20.)]);
+        assert!(top(&backend, 1).is_empty());
+    }
+
+    #[test]
+    fn keeps_only_the_top_entries() {
+        let backend = MockBackend::trusting();
+        for i in 0..MAX_ENTRIES {
+            submit(&backend, submission(5, 10. + i as f32, "a:1.1")).unwrap();
+        }
+        let response = submit(&backend, submission(5, 50., "a:1.1")).unwrap();
+        assert_eq!(response.place, None);
+        assert_eq!(top(&backend, 0).len(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn rejects_submissions_that_dont_match_their_layout() {
+        let backend = MockBackend::default();
+        // fewer colliders than enabled walls
+        assert!(submit(&backend, submission(1, 20., "a:1.1,2.1|b:-3.4")).is_err());
+        // over the level's budget
+        assert!(submit(&backend, submission(21, 20., "a:1.1")).is_err());
+        assert!(submit(&backend, submission(4, 20., "a:1,1")).is_err());
+        assert!(submit(&backend, submission(4, 0., "a:1.1")).is_err());
+        assert!(submit(
+            &backend,
+            Submission {
+                level: LEVELS.len(),
+                ..submission(4, 20., "a:1.1")
+            }
+        )
+        .is_err());
+        assert!(top(&backend, 0).is_empty());
+    }
+
+    // a floor across the first room with two gaps, hopped over while running right
+    fn hopping_run(colliders: usize, time: f32) -> Submission {
+        let walls: Vec<_> = (5..=12)
+            .chain(16..=23)
+            .chain(27..=30)
+            .map(|x| format!("{x}.5"))
+            .collect();
+        let solution = format!("a315ac10-66b0-11ec-9cd7-99f223ad6ade:{}", walls.join(","));
+        // 2 holds right and 16 jump
+        let replay = Replay {
+            frames: (0..180)
+                .map(|frame| {
+                    let keys = if frame == 34 || frame == 90 {
+                        2 | 16
+                    } else {
+                        2
+                    };
+                    (Duration::from_micros(16667), keys)
+                })
+                .collect(),
+        };
+        Submission {
+            replay: replay.code(),
+            ..submission(colliders, time, &solution)
+        }
+    }
+
+    #[test]
+    fn accepts_a_replayed_win() {
+        let run = hopping_run(20, 1.);
+        let layout = EnabledColliders::from_solution_code(&run.entry.solution).unwrap();
+        let replay = Replay::from_code(&run.replay).unwrap();
+        let time = replay::play_back(0, layout, &replay).unwrap().time;
+
+        let backend = MockBackend::default();
+        assert_eq!(
+            submit(&backend, hopping_run(20, time)).unwrap().place,
+            Some(0)
+        );
+        assert!(submit(&backend, hopping_run(20, time + 1.)).is_err());
+        assert!(submit(&backend, hopping_run(19, time)).is_err());
+    }
+
+    #[test]
+    fn rejects_a_layout_that_cant_win() {
+        let run = hopping_run(2, 2.);
+        // only the start colliders under the player and the chest
+        let submission = Submission {
+            entry: LeaderboardEntry {
+                solution: "a315ac10-66b0-11ec-9cd7-99f223ad6ade:5.5,30.5".to_string(),
+                ..run.entry
+            },
+            ..run
+        };
+        assert!(matches!(
+            verify(&submission),
+            Err(ClientError::Rejected(reason)) if reason == ReplayError::Lost.to_string()
+        ));
+    }
+
+    #[test]
+    fn replays_round_trip() {
+        let code = "16667.0,16702.18,8333.2";
+        assert_eq!(Replay::from_code(code).unwrap().code(), code);
+        assert!(Replay::from_code("16667").is_none());
+    }
+
+    #[test]
+    fn solution_codes_round_trip() {
+        let code = "a:1.1,2.1|b:-3.4";
+        let layout = EnabledColliders::from_solution_code(code).unwrap();
+        assert_eq!(layout.count(), 3);
+        assert_eq!(layout.solution_code(), code);
+    }
+
+    #[test]
+    fn submissions_serialize_flat() {
+        let json = serde_json::to_value(submission(4, 20., "a:1.1")).unwrap();
+        assert_eq!(json["level"], 0);
+        assert_eq!(json["colliders"], 4);
+        assert_eq!(json["solution"], "a:1.1");
+    }
+}
@@ -2,8 +2,11 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::assets::LdtkProject;
 
 use crate::{
-    audio::AudioEvent, play::Playthrough, FontHandle, GameMode, HOVERED_BUTTON, NORMAL_BUTTON,
-    PRESSED_BUTTON, TEXT_COLOR,
+    audio::AudioEvent,
+    components::Hazard,
+    play::Playthrough,
+    transition::{FadeIn, SlideIn},
+    FontHandle, GameMode, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
 };
 
 pub struct LostPlugin;
@@ -19,6 +22,8 @@ impl Plugin for LostPlugin {
 #[derive(Component)]
 struct OnLostScreen;
 
+const SLIDE_DISTANCE: f32 = 600.0;
+
 fn exit_screen(mut commands: Commands, query: Query<Entity, With<OnLostScreen>>) {
     for entity in &mut query.iter() {
         commands.entity(entity).despawn_recursive();
@@ -53,101 +58,132 @@ fn setup(mut commands: Commands, playthrough: Res<Playthrough>, font: Res<FontHa
                     flex_direction: FlexDirection::Column,
                     ..default()
                 },
-                background_color: Color::rgba(0.3, 0.3, 0.3, 0.5).into(),
+                background_color: Color::rgba(0.3, 0.3, 0.3, 0.0).into(),
                 ..default()
             },
+            FadeIn::new(0.4, 0.5),
             OnLostScreen,
         ))
         .with_children(|parent| {
-            parent.spawn(
-                TextBundle::from_section(
-                    "You lost...",
-                    TextStyle {
-                        font_size: 60.0,
-                        color: TEXT_COLOR,
-                        font: font.0.clone(),
-                    },
-                )
-                .with_style(Style {
-                    margin: UiRect::all(Val::Px(50.0)),
-                    ..default()
-                }),
-            );
-            parent.spawn(
-                TextBundle::from_section(
-                    if playthrough.lost_chest {
-                        "Those nice pancakes fell..."
-                    } else if playthrough.lost_player {
-                        "You fell!"
-                    } else if playthrough.enemy_hit {
-                        "That eagle found a nice snack..."
-                    } else {
-                        "You're out of time!"
-                    },
-                    TextStyle {
-                        font_size: 30.0,
-                        color: TEXT_COLOR,
-                        font: font.0.clone(),
-                    },
-                )
-                .with_text_alignment(TextAlignment::Center)
-                .with_style(Style {
-                    margin: UiRect::all(Val::Px(50.0)),
-                    ..default()
-                }),
-            );
             parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        flex_direction: FlexDirection::Row,
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            top: Val::Px(-SLIDE_DISTANCE),
+                            align_items: AlignItems::Center,
+                            flex_direction: FlexDirection::Column,
+                            ..default()
+                        },
                         ..default()
                     },
-                    ..default()
-                })
+                    SlideIn::new(0.5, SLIDE_DISTANCE),
+                ))
                 .with_children(|parent| {
-                    parent
-                        .spawn((
-                            ButtonBundle {
-                                style: button_style.clone(),
-                                background_color: NORMAL_BUTTON.into(),
-                                border_color: BorderColor(HOVERED_BUTTON),
-                                ..default()
+                    parent.spawn(
+                        TextBundle::from_section(
+                            "You lost...",
+                            TextStyle {
+                                font_size: 60.0,
+                                color: TEXT_COLOR,
+                                font: font.0.clone(),
                             },
-                            ButtonAction::Menu,
-                        ))
-                        .with_children(|parent| {
-                            parent
-                                .spawn(TextBundle::from_section("Menu", button_text_style.clone()));
-                        });
-                    parent
-                        .spawn((
-                            ButtonBundle {
-                                style: button_style.clone(),
-                                background_color: NORMAL_BUTTON.into(),
-                                border_color: BorderColor(HOVERED_BUTTON),
-                                ..default()
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(50.0)),
+                            ..default()
+                        }),
+                    );
+                    parent.spawn(
+                        TextBundle::from_section(
+                            match (playthrough.lost_prop.as_deref(), playthrough.hazard) {
+                                (Some("Chest"), _) => "Those nice pancakes fell...".to_string(),
+                                (Some(prop), _) => {
+                                    format!("That {} was important...", prop.to_lowercase())
+                                }
+                                (None, Some(Hazard::Spikes)) => {
+                                    "Those spikes were sharp...".to_string()
+                                }
+                                (None, Some(Hazard::Water)) => {
+                                    "You forgot to come up for air...".to_string()
+                                }
+                                (None, Some(Hazard::Lava)) => {
+                                    "That wasn't a hot tub...".to_string()
+                                }
+                                (None, None) if playthrough.lost_player => "You fell!".to_string(),
+                                (None, None) if playthrough.enemy_hit => {
+                                    "That eagle found a nice snack...".to_string()
+                                }
+                                (None, None) => "You're out of time!".to_string(),
                             },
-                            ButtonAction::Retry,
-                        ))
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "Retry",
-                                button_text_style.clone(),
-                            ));
-                        });
+                            TextStyle {
+                                font_size: 30.0,
+                                color: TEXT_COLOR,
+                                font: font.0.clone(),
+                            },
+                        )
+                        .with_text_alignment(TextAlignment::Center)
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(50.0)),
+                            ..default()
+                        }),
+                    );
                     parent
-                        .spawn((
-                            ButtonBundle {
-                                style: button_style.clone(),
-                                background_color: NORMAL_BUTTON.into(),
-                                border_color: BorderColor(HOVERED_BUTTON),
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
                                 ..default()
                             },
-                            ButtonAction::Edit,
-                        ))
+                            ..default()
+                        })
                         .with_children(|parent| {
                             parent
-                                .spawn(TextBundle::from_section("Edit", button_text_style.clone()));
+                                .spawn((
+                                    ButtonBundle {
+                                        style: button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        border_color: BorderColor(HOVERED_BUTTON),
+                                        ..default()
+                                    },
+                                    ButtonAction::Menu,
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "Menu",
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        border_color: BorderColor(HOVERED_BUTTON),
+                                        ..default()
+                                    },
+                                    ButtonAction::Retry,
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "Retry",
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        border_color: BorderColor(HOVERED_BUTTON),
+                                        ..default()
+                                    },
+                                    ButtonAction::Edit,
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "Edit",
+                                        button_text_style.clone(),
+                                    ));
+                                });
                         });
                 });
         });
@@ -174,7 +210,7 @@ fn button_system(
     for (interaction, mut color, button) in &mut interaction_query {
         *color = match *interaction {
             Interaction::Pressed => {
-                audio_events.send(AudioEvent::Click);
+                audio_events.send(AudioEvent::new("click"));
                 match button {
                     ButtonAction::Edit => next_state.set(GameMode::Edit),
                     ButtonAction::Retry => next_state.set(GameMode::Play),
@@ -1,22 +1,44 @@
-use aabb_picking_backend::AabbPickingBackend;
 use bevy::prelude::*;
 
+use bevy_aabb_picking::AabbPickingBackend;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_embedded_assets::EmbeddedAssetPlugin;
 use bevy_mod_picking::DefaultPickingPlugins;
 use bevy_rapier2d::prelude::*;
 
-mod aabb_picking_backend;
+mod animation;
 mod audio;
+mod camera;
 mod components;
 mod crash;
 mod edit;
+mod leaderboard;
+#[cfg(feature = "online-leaderboard")]
+mod leaderboard_client;
 mod lost;
 mod menu;
+mod minimap;
+mod movement_sounds;
+mod music;
+mod particles;
+mod pause;
 mod play;
+#[cfg(feature = "online-leaderboard")]
+mod replay;
+mod rooms;
+mod settings;
+mod speedrun;
+mod touch;
+mod transition;
 mod won;
 
 fn main() {
+    // lets a leaderboard server replay submissions with the game itself
+    #[cfg(feature = "online-leaderboard")]
+    if std::env::args().nth(1).as_deref() == Some("verify") {
+        return leaderboard_client::verify_stdin();
+    }
+
     App::new()
         .add_plugins((
             EmbeddedAssetPlugin {
@@ -24,6 +46,10 @@ fn main() {
             },
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
+                .set(bevy::audio::AudioPlugin {
+                    spatial_scale: bevy::audio::SpatialScale::new_2d(audio::SPATIAL_SCALE),
+                    ..default()
+                })
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         title: "Worst Physics Engine Ever".to_string(),
@@ -33,6 +59,7 @@ fn main() {
                     ..default()
                 }),
             LdtkPlugin,
+            components::ComponentsPlugin,
             RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0),
             DefaultPickingPlugins,
             AabbPickingBackend,
@@ -51,18 +78,28 @@ fn main() {
             play::PlayPlugin,
             menu::MenuPlugin,
             crash::CrashPlugin,
-            audio::AudioPlugin,
+            (
+                audio::AudioPlugin,
+                music::MusicPlugin,
+                movement_sounds::MovementSoundsPlugin,
+            ),
+            (
+                animation::AnimationPlugin,
+                particles::ParticlesPlugin,
+                transition::TransitionPlugin,
+            ),
+            rooms::RoomsPlugin,
+            camera::CameraPlugin,
+            minimap::MinimapPlugin,
+            touch::TouchPlugin,
+            pause::PausePlugin,
+            (settings::SettingsPlugin, leaderboard::LeaderboardPlugin),
+            speedrun::SpeedrunPlugin,
         ))
         .add_systems(Startup, setup)
-        .register_ldtk_int_cell::<components::WallBundle>(1)
-        .register_ldtk_int_cell::<components::LadderBundle>(2)
-        .register_ldtk_int_cell::<components::WallBundle>(3)
-        .register_ldtk_entity::<components::PlayerBundle>("Player")
-        .register_ldtk_entity::<components::MobBundle>("Mob")
-        .register_ldtk_entity::<components::ChestBundle>("Chest")
-        .register_ldtk_entity::<components::PumpkinsBundle>("Pumpkins")
         .add_state::<GameMode>()
         .add_state::<GameKind>()
+        .add_state::<PauseState>()
         .run();
 }
 
@@ -83,6 +120,16 @@ enum GameMode {
     Won,
     Lost,
     Crash,
+    Settings,
+    Leaderboards,
+}
+
+// Only used while in `GameMode::Play`, and reset to running when leaving it.
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+enum PauseState {
+    #[default]
+    Running,
+    Paused,
 }
 
 const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
@@ -97,11 +144,13 @@ pub struct FontHandle(Handle<Font>);
 #[derive(Resource)]
 pub struct LdtkHandle(Handle<LdtkProject>);
 
+const LDTK_PROJECT: &str = "Typical_2D_platformer_example.ldtk";
+
 pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     let camera = Camera2dBundle::default();
-    commands.spawn(camera);
+    commands.spawn((camera, SpatialListener::new(audio::EAR_GAP)));
 
-    let world = asset_server.load("Typical_2D_platformer_example.ldtk");
+    let world = asset_server.load(LDTK_PROJECT);
     commands.insert_resource(LdtkHandle(world));
 
     let font = asset_server.load("PublicPixel-z84yD.ttf");
@@ -120,7 +169,12 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         levels
     };
 
-    commands.insert_resource(Progression { levels });
+    commands.insert_resource(Progression {
+        levels,
+        time_trials: vec![usize::MAX; LEVELS.len()],
+        records: vec![None; LEVELS.len()],
+        best_times: vec![None; LEVELS.len()],
+    });
 }
 
 #[derive(Resource, Clone)]
@@ -128,38 +182,77 @@ pub struct LevelInfo {
     pub start_colliders: [GridCoords; 2],
     pub thresholds: [usize; 3],
     pub max_colliders: usize,
+    // seconds for the gold, silver and bronze time trial stars
+    pub time_thresholds: [f32; 3],
+    pub time_limit: f32,
 }
 
-const LEVELS: [LevelInfo; 6] = [
+impl LevelInfo {
+    /// 0 for gold, 1 for silver, 2 for bronze and 3 for no time trial star.
+    pub fn time_rank(&self, time: f32) -> usize {
+        self.time_thresholds
+            .iter()
+            .filter(|threshold| time > **threshold)
+            .count()
+    }
+}
+
+const LEVELS: [LevelInfo; 8] = [
     LevelInfo {
         start_colliders: [GridCoords { x: 5, y: 5 }, GridCoords { x: 30, y: 5 }],
         thresholds: [5, 8, 10],
         max_colliders: 20,
+        time_thresholds: [15.0, 25.0, 40.0],
+        time_limit: 60.0,
     },
     LevelInfo {
         start_colliders: [GridCoords { x: 5, y: 5 }, GridCoords { x: 30, y: 5 }],
         thresholds: [5, 8, 10],
         max_colliders: 20,
+        time_thresholds: [15.0, 25.0, 40.0],
+        time_limit: 60.0,
     },
     LevelInfo {
         start_colliders: [GridCoords { x: 5, y: 5 }, GridCoords { x: 30, y: 5 }],
         thresholds: [5, 8, 10],
         max_colliders: 20,
+        time_thresholds: [15.0, 25.0, 40.0],
+        time_limit: 60.0,
     },
     LevelInfo {
         start_colliders: [GridCoords { x: 5, y: 5 }, GridCoords { x: 30, y: 5 }],
         thresholds: [5, 8, 10],
         max_colliders: 20,
+        time_thresholds: [15.0, 25.0, 40.0],
+        time_limit: 60.0,
     },
     LevelInfo {
         start_colliders: [GridCoords { x: 1, y: 15 }, GridCoords { x: 34, y: 1 }],
         thresholds: [7, 10, 13],
         max_colliders: 20,
+        time_thresholds: [25.0, 40.0, 60.0],
+        time_limit: 90.0,
     },
     LevelInfo {
         start_colliders: [GridCoords { x: 1, y: 15 }, GridCoords { x: 34, y: 1 }],
         thresholds: [7, 10, 13],
         max_colliders: 20,
+        time_thresholds: [25.0, 40.0, 60.0],
+        time_limit: 90.0,
+    },
+    LevelInfo {
+        start_colliders: [GridCoords { x: 5, y: 5 }, GridCoords { x: 30, y: 5 }],
+        thresholds: [8, 11, 14],
+        max_colliders: 20,
+        time_thresholds: [30.0, 45.0, 70.0],
+        time_limit: 100.0,
+    },
+    LevelInfo {
+        start_colliders: [GridCoords { x: 3, y: 5 }, GridCoords { x: 30, y: 5 }],
+        thresholds: [6, 9, 12],
+        max_colliders: 20,
+        time_thresholds: [25.0, 40.0, 60.0],
+        time_limit: 90.0,
     },
 ];
 
@@ -169,4 +262,22 @@ struct CurrentLevel(usize);
 #[derive(Resource)]
 pub struct Progression {
     pub levels: Vec<usize>,
+    // time trial stars, ranked like `levels`
+    pub time_trials: Vec<usize>,
+    // personal best of each level, fewest colliders first then fastest
+    pub records: Vec<Option<LevelRecord>>,
+    // fastest win of each level whatever the colliders, for the time trial
+    pub best_times: Vec<Option<f32>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelRecord {
+    pub colliders: usize,
+    pub time: f32,
+}
+
+impl LevelRecord {
+    pub fn beats(&self, other: &LevelRecord) -> bool {
+        (self.colliders, self.time) < (other.colliders, other.time)
+    }
 }
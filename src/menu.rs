@@ -1,12 +1,14 @@
 use std::f32::consts::FRAC_PI_3;
 
-use bevy::{prelude::*, utils::HashSet};
-use bevy_ecs_ldtk::{LdtkWorldBundle, LevelSelection};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{assets::LdtkProject, LdtkWorldBundle, LevelSelection};
 
 use crate::{
-    audio::AudioEvent, edit::EnabledColliders, CurrentLevel, FontHandle, GameKind, GameMode,
-    LdtkHandle, Progression, DISABLED_BUTTON, HOVERED_BUTTON, LEVELS, NORMAL_BUTTON,
-    PRESSED_BUTTON, TEXT_COLOR,
+    audio::AudioEvent,
+    edit::EnabledColliders,
+    rooms::{stage_platforms, start_room},
+    CurrentLevel, FontHandle, GameKind, GameMode, LdtkHandle, Progression, DISABLED_BUTTON,
+    HOVERED_BUTTON, LEVELS, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
 };
 
 pub struct MenuPlugin;
@@ -164,18 +166,76 @@ fn setup(
                                     ..default()
                                 });
                             }
+                            // the time trial star sits on the other corner
+                            if progression.time_trials[i] < 3 {
+                                parent.spawn(ImageBundle {
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        left: Val::Px(-8.0),
+                                        top: Val::Px(-8.0),
+                                        width: Val::Px(20.0),
+                                        height: Val::Px(20.0),
+                                        ..default()
+                                    },
+                                    image: UiImage::new(asset_server.load(
+                                        match progression.time_trials[i] {
+                                            0 => "starGold.png",
+                                            1 => "starSilver.png",
+                                            2 => "starBronze.png",
+                                            _ => unreachable!(),
+                                        },
+                                    )),
+                                    transform: Transform::from_rotation(Quat::from_rotation_z(
+                                        FRAC_PI_3,
+                                    )),
+                                    ..default()
+                                });
+                            }
                         });
                         if enabled {
                             button.insert(ButtonAction::Start(i));
                         }
                     }
                 });
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (action, label) in [
+                        (ButtonAction::Leaderboards, "Scores"),
+                        (ButtonAction::Settings, "Settings"),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    border_color: BorderColor(HOVERED_BUTTON),
+                                    ..default()
+                                },
+                                action,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    label,
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+                });
         });
 }
 
 #[derive(Component)]
 enum ButtonAction {
     Start(usize),
+    Leaderboards,
+    Settings,
 }
 
 #[allow(clippy::type_complexity)]
@@ -187,31 +247,33 @@ fn button_system(
     >,
     mut next_state: ResMut<NextState<GameMode>>,
     world: Res<LdtkHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
     game_kind: Res<State<GameKind>>,
     mut audio_events: EventWriter<AudioEvent>,
 ) {
     for (interaction, mut color, button) in &mut interaction_query {
         *color = match *interaction {
             Interaction::Pressed => {
-                audio_events.send(AudioEvent::Click);
+                audio_events.send(AudioEvent::new("click"));
                 match button {
                     ButtonAction::Start(level) => {
                         match game_kind.get() {
                             GameKind::Platformer => next_state.set(GameMode::Play),
                             GameKind::Puzzle => next_state.set(GameMode::Edit),
                         };
-                        let mut coords = HashSet::new();
-                        for starter in &LEVELS[*level].start_colliders {
-                            coords.insert(*starter);
-                        }
-                        commands.insert_resource(EnabledColliders { coords });
-                        commands.insert_resource(LevelSelection::index(*level));
-                        commands.insert_resource(CurrentLevel(*level));
+                        load_level(
+                            &mut commands,
+                            ldtk_project_assets.get(&world.0),
+                            *level,
+                            game_kind.get(),
+                        );
                         commands.spawn(LdtkWorldBundle {
                             ldtk_handle: world.0.clone(),
                             ..Default::default()
                         });
                     }
+                    ButtonAction::Leaderboards => next_state.set(GameMode::Leaderboards),
+                    ButtonAction::Settings => next_state.set(GameMode::Settings),
                 }
                 PRESSED_BUTTON.into()
             }
@@ -220,3 +282,25 @@ fn button_system(
         }
     }
 }
+
+/// Selects the first room of a stage with its starting colliders.
+pub fn load_level(
+    commands: &mut Commands,
+    ldtk_project: Option<&LdtkProject>,
+    level: usize,
+    game_kind: &GameKind,
+) {
+    let mut colliders = EnabledColliders::default();
+    colliders.reset(
+        ldtk_project.and_then(|ldtk_project| start_room(ldtk_project, level)),
+        &LEVELS[level],
+    );
+    // platforms only use up the budget when there are colliders to pick
+    if matches!(game_kind, GameKind::Puzzle) {
+        colliders.platforms =
+            ldtk_project.map_or(0, |ldtk_project| stage_platforms(ldtk_project, level));
+    }
+    commands.insert_resource(colliders);
+    commands.insert_resource(LevelSelection::index(level));
+    commands.insert_resource(CurrentLevel(level));
+}
@@ -0,0 +1,353 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    utils::HashSet,
+};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    audio::AudioEvent,
+    components::{Chest, Enemy, Player},
+    edit::EnabledColliders,
+    FontHandle, GameKind, GameMode, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
+};
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapSettings>()
+            .add_systems(
+                Update,
+                (toggle_minimap, draw_minimap, update_markers)
+                    .run_if(in_state(GameMode::Play).or_else(in_state(GameMode::Edit))),
+            )
+            .add_systems(OnEnter(GameMode::Edit), setup_minimap)
+            .add_systems(OnEnter(GameMode::Play), setup_minimap)
+            .add_systems(OnExit(GameMode::Edit), exit_mode)
+            .add_systems(OnExit(GameMode::Play), exit_mode);
+    }
+}
+
+#[derive(Resource)]
+pub struct MinimapSettings {
+    pub visible: bool,
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        MinimapSettings { visible: true }
+    }
+}
+
+#[derive(Component)]
+struct OnMinimap;
+
+#[derive(Component)]
+struct MinimapToggle;
+
+#[derive(Component, Default)]
+struct Minimap {
+    // room the image was drawn for
+    level_iid: Option<String>,
+}
+
+// Entity shown by a dot on the minimap.
+#[derive(Component)]
+struct MinimapMarker(Entity);
+
+const WIDTH: f32 = 200.0;
+
+fn exit_mode(mut commands: Commands, query: Query<Entity, With<OnMinimap>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn setup_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<MinimapSettings>,
+    font: Res<FontHandle>,
+) {
+    // redrawn from the collisions layer once the level has spawned
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(12.0),
+                left: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(OnMinimap)
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(80.0),
+                            height: Val::Px(40.0),
+                            margin: UiRect::bottom(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            border: UiRect::all(Val::Px(5.0)),
+                            ..default()
+                        },
+                        background_color: NORMAL_BUTTON.into(),
+                        border_color: BorderColor(HOVERED_BUTTON),
+                        ..default()
+                    },
+                    MinimapToggle,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Map",
+                        TextStyle {
+                            font_size: 20.0,
+                            color: TEXT_COLOR,
+                            font: font.0.clone(),
+                        },
+                    ));
+                });
+
+            parent.spawn((
+                ImageBundle {
+                    style: Style {
+                        width: Val::Px(WIDTH),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    image: UiImage::new(image),
+                    background_color: Color::WHITE.with_a(0.8).into(),
+                    visibility: if settings.visible {
+                        Visibility::Inherited
+                    } else {
+                        Visibility::Hidden
+                    },
+                    ..default()
+                },
+                Minimap::default(),
+            ));
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn toggle_minimap(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<MinimapToggle>),
+    >,
+    mut minimap: Query<&mut Visibility, With<Minimap>>,
+    mut settings: ResMut<MinimapSettings>,
+    keys: Res<Input<KeyCode>>,
+    mut audio_events: EventWriter<AudioEvent>,
+) {
+    let mut toggle = keys.just_pressed(KeyCode::M);
+    for (interaction, mut color) in &mut interaction_query {
+        *color = match *interaction {
+            Interaction::Pressed => {
+                audio_events.send(AudioEvent::new("click"));
+                toggle = true;
+                PRESSED_BUTTON.into()
+            }
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        }
+    }
+
+    if toggle {
+        settings.visible = !settings.visible;
+        for mut visibility in &mut minimap {
+            *visibility = if settings.visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
+
+fn cell_color(value: i32) -> [u8; 4] {
+    match value {
+        // walls
+        1 | 3 => [110, 110, 120, 255],
+        // ladders
+        2 => [150, 100, 50, 255],
+        // spikes
+        4 => [200, 200, 200, 255],
+        // water
+        5 => [50, 90, 200, 255],
+        // lava
+        6 => [230, 100, 20, 255],
+        _ => [0, 0, 0, 160],
+    }
+}
+
+fn draw_minimap(
+    mut minimap: Query<(&mut Minimap, &UiImage, &mut Style)>,
+    level_query: Query<&LevelIid>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut images: ResMut<Assets<Image>>,
+    enabled: Res<EnabledColliders>,
+    game_kind: Res<State<GameKind>>,
+) {
+    let Some(level_iid) = level_query.iter().next().map(|iid| iid.to_string()) else {
+        return;
+    };
+    for (mut minimap, ui_image, mut style) in &mut minimap {
+        if minimap.level_iid.as_ref() == Some(&level_iid) && !enabled.is_changed() {
+            continue;
+        }
+        let Some(level) = ldtk_project_assets
+            .get(ldtk_projects.single())
+            .and_then(|ldtk_project| ldtk_project.get_raw_level_by_iid(&level_iid))
+        else {
+            continue;
+        };
+        let Some(layer) = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .find(|layer| layer.identifier == "Collisions")
+        else {
+            continue;
+        };
+        let Some(image) = images.get_mut(&ui_image.texture) else {
+            continue;
+        };
+
+        let (width, height) = (layer.c_wid as usize, layer.c_hei as usize);
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let mut data = Vec::with_capacity(width * height * 4);
+        for value in &layer.int_grid_csv {
+            data.extend_from_slice(&cell_color(*value));
+        }
+        data.resize(width * height * 4, 0);
+
+        if matches!(game_kind.get(), GameKind::Puzzle) {
+            for coords in enabled.coords.get(&level_iid).into_iter().flatten() {
+                // grid coordinates start at the bottom, image rows at the top
+                let (x, y) = (
+                    coords.x as usize,
+                    (height - 1).wrapping_sub(coords.y as usize),
+                );
+                if x < width && y < height {
+                    let pixel = (y * width + x) * 4;
+                    data[pixel..pixel + 4].copy_from_slice(&[40, 220, 40, 255]);
+                }
+            }
+        }
+
+        *image = Image::new(
+            Extent3d {
+                width: width as u32,
+                height: height as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        style.aspect_ratio = Some(width as f32 / height as f32);
+        minimap.level_iid = Some(level_iid.clone());
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_markers(
+    mut commands: Commands,
+    minimap: Query<Entity, With<Minimap>>,
+    mut markers: Query<(Entity, &MinimapMarker, &mut Style)>,
+    tracked: Query<
+        (Entity, &GlobalTransform, Option<&Player>, Option<&Chest>),
+        Or<(With<Player>, With<Chest>, With<Enemy>)>,
+    >,
+    level_query: Query<(&GlobalTransform, &LevelIid)>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Ok(minimap) = minimap.get_single() else {
+        return;
+    };
+    let Some((level_transform, level_iid)) = level_query.iter().next() else {
+        return;
+    };
+    let Some(level) = ldtk_project_assets
+        .get(ldtk_projects.single())
+        .and_then(|ldtk_project| ldtk_project.get_raw_level_by_iid(&level_iid.to_string()))
+    else {
+        return;
+    };
+    let level_origin = level_transform.translation().truncate();
+    let level_size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+
+    let mut shown = HashSet::new();
+    for (marker_entity, marker, mut style) in &mut markers {
+        let Ok((_, transform, ..)) = tracked.get(marker.0) else {
+            commands.entity(marker_entity).despawn_recursive();
+            continue;
+        };
+        shown.insert(marker.0);
+        let position = (transform.translation().truncate() - level_origin) / level_size;
+        style.left = Val::Percent(position.x.clamp(0., 1.) * 100.);
+        style.bottom = Val::Percent(position.y.clamp(0., 1.) * 100.);
+    }
+
+    for (entity, transform, player, chest) in &tracked {
+        if shown.contains(&entity) {
+            continue;
+        }
+        let position = (transform.translation().truncate() - level_origin) / level_size;
+        let (color, size) = if player.is_some() {
+            (Color::WHITE, 6.)
+        } else if chest.is_some() {
+            (Color::GOLD, 6.)
+        } else {
+            (Color::RED, 4.)
+        };
+        let marker = commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Percent(position.x.clamp(0., 1.) * 100.),
+                        bottom: Val::Percent(position.y.clamp(0., 1.) * 100.),
+                        width: Val::Px(size),
+                        height: Val::Px(size),
+                        // center the dot on the position
+                        margin: UiRect {
+                            left: Val::Px(-size / 2.),
+                            bottom: Val::Px(-size / 2.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    // player on top
+                    z_index: ZIndex::Local(if player.is_some() { 2 } else { 1 }),
+                    ..default()
+                },
+                MinimapMarker(entity),
+            ))
+            .id();
+        commands.entity(minimap).add_child(marker);
+    }
+}
@@ -0,0 +1,81 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    audio::{AudioEvent, Emitter},
+    components::*,
+    particles::{ParticleEvent, ParticleKind},
+    GameMode, PauseState,
+};
+
+pub struct MovementSoundsPlugin;
+
+impl Plugin for MovementSoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (landing, chest_slide)
+                .run_if(in_state(GameMode::Play))
+                .run_if(in_state(PauseState::Running)),
+        );
+    }
+}
+
+// Falling speed in pixels per second under which landing is silent, and at which it is loudest.
+const LANDING_THRESHOLD: f32 = 250.0;
+const LANDING_LOUDEST: f32 = 800.0;
+
+const SLIDE_THRESHOLD: f32 = 40.0;
+const SLIDE_LOUDEST: f32 = 300.0;
+
+fn landing(
+    bodies: Query<(Entity, &GroundDetection, &Velocity, Has<Chest>)>,
+    // whether each body was on the ground and how fast it was falling on the previous frame
+    mut previous: Local<HashMap<Entity, (bool, f32)>>,
+    mut audio_events: EventWriter<AudioEvent>,
+    mut particle_events: EventWriter<ParticleEvent>,
+) {
+    // the velocity is already absorbed on the frame the ground is touched, use the previous one
+    for (entity, ground_detection, velocity, chest) in &bodies {
+        let falling = (-velocity.linvel.y).max(0.0);
+        let (was_on_ground, was_falling) = previous
+            .insert(entity, (ground_detection.on_ground, falling))
+            .unwrap_or((true, 0.0));
+        if was_on_ground || !ground_detection.on_ground || was_falling < LANDING_THRESHOLD {
+            continue;
+        }
+
+        let intensity = ((was_falling - LANDING_THRESHOLD) / (LANDING_LOUDEST - LANDING_THRESHOLD))
+            .clamp(0.0, 1.0);
+        let name = if chest { "chest_land" } else { "land" };
+        audio_events.send(
+            AudioEvent::new(name)
+                .with_entity(entity)
+                .with_volume(0.3 + 0.7 * intensity),
+        );
+        particle_events.send(ParticleEvent::new(
+            ParticleKind::Dust,
+            Emitter::Entity(entity),
+        ));
+    }
+    previous.retain(|entity, _| bodies.contains(*entity));
+}
+
+// Repeats while the chest slides, the sound bank cooldown spaces them out.
+fn chest_slide(
+    chests: Query<(Entity, &GroundDetection, &Velocity), With<Chest>>,
+    mut audio_events: EventWriter<AudioEvent>,
+) {
+    for (entity, ground_detection, velocity) in &chests {
+        let speed = velocity.linvel.x.abs();
+        if !ground_detection.on_ground || speed < SLIDE_THRESHOLD {
+            continue;
+        }
+        let intensity = (speed / SLIDE_LOUDEST).clamp(0.0, 1.0);
+        audio_events.send(
+            AudioEvent::new("chest_slide")
+                .with_entity(entity)
+                .with_volume(0.2 + 0.8 * intensity),
+        );
+    }
+}
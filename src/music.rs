@@ -0,0 +1,178 @@
+use bevy::{audio::Volume, prelude::*};
+
+use crate::{audio::AudioEvent, settings::Settings, CurrentLevel, GameMode, PauseState};
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Music>()
+            .init_resource::<MusicTracks>()
+            .add_systems(Update, (select_track, duck, play_music).chain());
+    }
+}
+
+/// The music being played, the track can be changed at any time and crossfades.
+#[derive(Resource)]
+pub struct Music {
+    /// Asset path of the looping track to play, `None` for silence.
+    pub track: Option<String>,
+    /// Music volume on top of the settings one, between 0 and 1.
+    pub volume: f32,
+    /// Duration of a crossfade in seconds.
+    pub crossfade: f32,
+    /// Relative volume of the music while a stinger plays.
+    pub duck_volume: f32,
+    duck: Timer,
+    duck_gain: f32,
+    pause_gain: f32,
+}
+
+impl Default for Music {
+    fn default() -> Self {
+        let mut duck = Timer::from_seconds(3.0, TimerMode::Once);
+        duck.tick(duck.duration());
+        Music {
+            track: None,
+            volume: 1.0,
+            crossfade: 1.5,
+            duck_volume: 0.25,
+            duck,
+            duck_gain: 1.0,
+            pause_gain: 1.0,
+        }
+    }
+}
+
+/// Tracks played in each game mode.
+#[derive(Resource)]
+pub struct MusicTracks {
+    pub menu: String,
+    pub edit: String,
+    pub play: String,
+    pub crash: String,
+    /// Replaces the play track for the levels that have one.
+    pub levels: Vec<Option<String>>,
+}
+
+impl Default for MusicTracks {
+    fn default() -> Self {
+        MusicTracks {
+            menu: "music/menu.ogg".to_string(),
+            edit: "music/edit.ogg".to_string(),
+            play: "music/play.ogg".to_string(),
+            crash: "music/crash.ogg".to_string(),
+            levels: vec![],
+        }
+    }
+}
+
+#[derive(Component)]
+struct MusicTrack {
+    path: String,
+    gain: f32,
+}
+
+fn select_track(
+    game_mode: Res<State<GameMode>>,
+    level: Option<Res<CurrentLevel>>,
+    tracks: Res<MusicTracks>,
+    mut music: ResMut<Music>,
+) {
+    if !game_mode.is_changed() {
+        return;
+    }
+    let track = match game_mode.get() {
+        GameMode::Menu | GameMode::Settings | GameMode::Leaderboards => tracks.menu.clone(),
+        GameMode::Edit => tracks.edit.clone(),
+        GameMode::Play => level
+            .and_then(|level| tracks.levels.get(level.0).cloned().flatten())
+            .unwrap_or_else(|| tracks.play.clone()),
+        GameMode::Crash => tracks.crash.clone(),
+        // keep the music of the level going under the stinger
+        GameMode::Won | GameMode::Lost => return,
+    };
+    if music.track.as_ref() != Some(&track) {
+        music.track = Some(track);
+    }
+}
+
+fn duck(mut audio_events: EventReader<AudioEvent>, mut music: ResMut<Music>) {
+    for event in audio_events.read() {
+        if matches!(event.name(), "win" | "crash") {
+            music.duck.reset();
+        }
+    }
+}
+
+// Moves `value` towards `target` by at most `step`.
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    value + (target - value).clamp(-step, step)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play_music(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut music: ResMut<Music>,
+    settings: Res<Settings>,
+    pause_state: Res<State<PauseState>>,
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+
+    if let Some(track) = &music.track {
+        if !tracks.iter().any(|(_, playing, _)| playing.path == *track) {
+            commands.spawn((
+                AudioBundle {
+                    source: asset_server.load(track.clone()),
+                    settings: PlaybackSettings::LOOP.with_volume(Volume::new_absolute(0.0)),
+                },
+                MusicTrack {
+                    path: track.clone(),
+                    gain: 0.0,
+                },
+            ));
+        }
+    }
+
+    let Music {
+        duck,
+        duck_gain,
+        pause_gain,
+        duck_volume,
+        ..
+    } = music.as_mut();
+    duck.tick(time.delta());
+    let duck_target = if duck.finished() { 1.0 } else { *duck_volume };
+    *duck_gain = approach(*duck_gain, duck_target, dt * 2.0);
+    let paused = *pause_state.get() == PauseState::Paused;
+    *pause_gain = approach(*pause_gain, if paused { 0.0 } else { 1.0 }, dt * 2.0);
+
+    let step = dt / music.crossfade.max(0.01);
+    let volume = music.volume * music.duck_gain * music.pause_gain * settings.music();
+    for (entity, mut playing, sink) in &mut tracks {
+        let target = if music.track.as_ref() == Some(&playing.path) {
+            1.0
+        } else {
+            0.0
+        };
+        playing.gain = approach(playing.gain, target, step);
+        if playing.gain == 0.0 && target == 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        // the sink only exists once the track has loaded
+        let Some(sink) = sink else {
+            continue;
+        };
+        sink.set_volume(playing.gain * volume);
+        if music.pause_gain == 0.0 {
+            sink.pause();
+        } else if sink.is_paused() {
+            sink.play();
+        }
+    }
+}
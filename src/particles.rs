@@ -0,0 +1,222 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use bevy_mod_picking::prelude::Pickable;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{audio::Emitter, play::RestartPlaythrough, GameMode, PauseState};
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ParticleEvent>()
+            .insert_resource(ParticleRng::new())
+            .add_systems(Startup, spawn_pool)
+            .add_systems(Update, (reset_rng, spawn_bursts).chain())
+            // a fixed step keeps the particles identical when a playthrough is replayed
+            .add_systems(
+                FixedUpdate,
+                update_particles.run_if(in_state(PauseState::Running)),
+            )
+            .add_systems(OnEnter(GameMode::Play), (clear, reset_seed))
+            .add_systems(OnEnter(GameMode::Edit), clear)
+            .add_systems(OnEnter(GameMode::Menu), clear);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParticleKind {
+    /// Puffs at the feet when jumping or landing.
+    Dust,
+    /// Burst over a collider that was enabled.
+    Sparkle,
+    /// Red flash over a collider that could not be enabled.
+    FailFlash,
+    /// Falls all over the chest when the level is won.
+    Confetti,
+}
+
+/// Spawns a burst of particles where the emitter is.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ParticleEvent {
+    pub kind: ParticleKind,
+    pub emitter: Emitter,
+}
+
+impl ParticleEvent {
+    pub fn new(kind: ParticleKind, emitter: Emitter) -> Self {
+        ParticleEvent { kind, emitter }
+    }
+}
+
+// Particles are recycled oldest first once all of them are in use.
+const POOL_SIZE: usize = 512;
+const SEED: u64 = 0x5eed;
+
+#[derive(Resource)]
+struct ParticlePool {
+    particles: Vec<Entity>,
+    next: usize,
+}
+
+// Every burst draws from this generator, which restarts with each playthrough.
+#[derive(Resource)]
+struct ParticleRng(StdRng);
+
+impl ParticleRng {
+    fn new() -> Self {
+        ParticleRng(StdRng::seed_from_u64(SEED))
+    }
+}
+
+#[derive(Component, Default)]
+struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    age: f32,
+    lifetime: f32,
+    size: f32,
+    color: Color,
+}
+
+fn spawn_pool(mut commands: Commands) {
+    let particles = (0..POOL_SIZE)
+        .map(|_| {
+            commands
+                .spawn((
+                    SpriteBundle {
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    Particle::default(),
+                    // never in the way of clicks on colliders
+                    Pickable::IGNORE,
+                ))
+                .id()
+        })
+        .collect();
+    commands.insert_resource(ParticlePool { particles, next: 0 });
+}
+
+fn clear(mut particles: Query<(&mut Particle, &mut Visibility)>) {
+    for (mut particle, mut visibility) in &mut particles {
+        particle.age = particle.lifetime;
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn reset_seed(mut rng: ResMut<ParticleRng>) {
+    *rng = ParticleRng::new();
+}
+
+fn reset_rng(mut restarts: EventReader<RestartPlaythrough>, mut rng: ResMut<ParticleRng>) {
+    if restarts.read().count() > 0 {
+        *rng = ParticleRng::new();
+    }
+}
+
+fn spawn_bursts(
+    mut events: EventReader<ParticleEvent>,
+    mut pool: ResMut<ParticlePool>,
+    mut rng: ResMut<ParticleRng>,
+    mut particles: Query<(&mut Particle, &mut Transform, &mut Sprite, &mut Visibility)>,
+    transforms: Query<&GlobalTransform, Without<Particle>>,
+) {
+    let rng = &mut rng.0;
+    for event in events.read() {
+        let origin = match event.emitter {
+            Emitter::Entity(entity) => match transforms.get(entity) {
+                Ok(transform) => transform.translation().truncate(),
+                Err(_) => continue,
+            },
+            Emitter::Position(position) => position,
+        };
+
+        let (count, offset) = match event.kind {
+            ParticleKind::Dust => (6, Vec2::new(0., -10.)),
+            ParticleKind::Sparkle => (12, Vec2::ZERO),
+            ParticleKind::FailFlash => (1, Vec2::ZERO),
+            ParticleKind::Confetti => (60, Vec2::new(0., 8.)),
+        };
+        for _ in 0..count {
+            let particle = match event.kind {
+                ParticleKind::Dust => Particle {
+                    velocity: Vec2::new(rng.gen_range(-60.0..60.0), rng.gen_range(0.0..25.0)),
+                    gravity: 0.,
+                    lifetime: rng.gen_range(0.3..0.5),
+                    size: rng.gen_range(2.0..4.0),
+                    color: Color::rgb(0.85, 0.8, 0.7),
+                    ..default()
+                },
+                ParticleKind::Sparkle => {
+                    let angle = rng.gen_range(0.0..TAU);
+                    Particle {
+                        velocity: Vec2::from_angle(angle) * rng.gen_range(40.0..100.0),
+                        gravity: 0.,
+                        lifetime: rng.gen_range(0.3..0.6),
+                        size: 2.,
+                        color: Color::rgb(1.0, 1.0, rng.gen_range(0.4..1.0)),
+                        ..default()
+                    }
+                }
+                ParticleKind::FailFlash => Particle {
+                    velocity: Vec2::ZERO,
+                    gravity: 0.,
+                    lifetime: 0.25,
+                    size: 16.,
+                    color: Color::rgba(1.0, 0.1, 0.1, 0.8),
+                    ..default()
+                },
+                ParticleKind::Confetti => {
+                    let angle = rng.gen_range(PI * 0.25..PI * 0.75);
+                    Particle {
+                        velocity: Vec2::from_angle(angle) * rng.gen_range(150.0..350.0),
+                        gravity: 400.,
+                        lifetime: rng.gen_range(1.5..2.0),
+                        size: 3.,
+                        color: Color::hsl(rng.gen_range(0.0..360.0), 0.9, 0.6),
+                        ..default()
+                    }
+                }
+            };
+
+            let entity = pool.particles[pool.next];
+            pool.next = (pool.next + 1) % pool.particles.len();
+            let Ok((mut slot, mut transform, mut sprite, mut visibility)) =
+                particles.get_mut(entity)
+            else {
+                continue;
+            };
+            transform.translation = (origin + offset).extend(50.);
+            sprite.color = particle.color;
+            sprite.custom_size = Some(Vec2::splat(particle.size));
+            *visibility = Visibility::Visible;
+            *slot = particle;
+        }
+    }
+}
+
+fn update_particles(
+    mut particles: Query<(&mut Particle, &mut Transform, &mut Sprite, &mut Visibility)>,
+    time: Res<Time<Fixed>>,
+) {
+    let dt = time.delta_seconds();
+    for (mut particle, mut transform, mut sprite, mut visibility) in &mut particles {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        particle.age += dt;
+        if particle.age >= particle.lifetime {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        particle.velocity.y -= particle.gravity * dt;
+        transform.translation += (particle.velocity * dt).extend(0.);
+
+        let left = 1. - particle.age / particle.lifetime;
+        sprite.color = particle.color.with_a(particle.color.a() * left);
+        sprite.custom_size = Some(Vec2::splat(particle.size * (0.5 + 0.5 * left)));
+    }
+}
@@ -0,0 +1,182 @@
+use bevy::{prelude::*, window::WindowFocused};
+use bevy_ecs_ldtk::assets::LdtkProject;
+use bevy_rapier2d::plugin::RapierConfiguration;
+
+use crate::{
+    audio::AudioEvent, play::RestartPlaythrough, FontHandle, GameKind, GameMode, PauseState,
+    HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
+};
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, pause.run_if(in_state(GameMode::Play)))
+            .add_systems(Update, button_system.run_if(in_state(PauseState::Paused)))
+            .add_systems(OnEnter(PauseState::Paused), setup)
+            .add_systems(OnExit(PauseState::Paused), exit_screen)
+            .add_systems(OnExit(GameMode::Play), resume);
+    }
+}
+
+fn pause(
+    input: Res<Input<KeyCode>>,
+    mut focus_events: EventReader<WindowFocused>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    let focus_lost = focus_events.read().any(|event| !event.focused);
+    match pause_state.get() {
+        PauseState::Running if focus_lost || input.just_pressed(KeyCode::Escape) => {
+            next_pause_state.set(PauseState::Paused)
+        }
+        PauseState::Paused if input.just_pressed(KeyCode::Escape) => {
+            next_pause_state.set(PauseState::Running)
+        }
+        _ => (),
+    }
+}
+
+fn resume(mut next_pause_state: ResMut<NextState<PauseState>>) {
+    next_pause_state.set(PauseState::Running);
+}
+
+#[derive(Component)]
+struct OnPauseScreen;
+
+fn exit_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<OnPauseScreen>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    for entity in &mut query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    rapier_config.physics_pipeline_active = true;
+}
+
+fn setup(
+    mut commands: Commands,
+    font: Res<FontHandle>,
+    game_kind: Res<State<GameKind>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    rapier_config.physics_pipeline_active = false;
+
+    let button_style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(5.0)),
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 25.0,
+        color: TEXT_COLOR,
+        font: font.0.clone(),
+    };
+
+    let mut actions = vec![
+        (ButtonAction::Resume, "Resume"),
+        (ButtonAction::Restart, "Restart"),
+    ];
+    if matches!(game_kind.get(), GameKind::Puzzle) {
+        actions.push((ButtonAction::Edit, "Edit"));
+    }
+    actions.push((ButtonAction::Menu, "Menu"));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            OnPauseScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Paused",
+                    TextStyle {
+                        font_size: 60.0,
+                        color: TEXT_COLOR,
+                        font: font.0.clone(),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(40.0)),
+                    ..default()
+                }),
+            );
+            for (action, label) in actions {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: button_style.clone(),
+                            background_color: NORMAL_BUTTON.into(),
+                            border_color: BorderColor(HOVERED_BUTTON),
+                            ..default()
+                        },
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(label, button_text_style.clone()));
+                    });
+            }
+        });
+}
+
+#[derive(Component)]
+enum ButtonAction {
+    Resume,
+    Restart,
+    Edit,
+    Menu,
+}
+
+#[allow(clippy::type_complexity)]
+fn button_system(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &ButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut next_state: ResMut<NextState<GameMode>>,
+    world_query: Query<Entity, With<Handle<LdtkProject>>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut restarts: EventWriter<RestartPlaythrough>,
+    mut audio_events: EventWriter<AudioEvent>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        *color = match *interaction {
+            Interaction::Pressed => {
+                audio_events.send(AudioEvent::new("click"));
+                next_pause_state.set(PauseState::Running);
+                match button {
+                    ButtonAction::Resume => (),
+                    ButtonAction::Restart => restarts.send(RestartPlaythrough),
+                    ButtonAction::Edit => next_state.set(GameMode::Edit),
+                    ButtonAction::Menu => {
+                        commands.entity(world_query.single()).despawn_recursive();
+                        next_state.set(GameMode::Menu);
+                    }
+                }
+                PRESSED_BUTTON.into()
+            }
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        }
+    }
+}
@@ -1,11 +1,20 @@
 use crate::{
-    audio::AudioEvent, components::*, edit::EnabledColliders, FontHandle, GameKind, GameMode,
-    HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
+    animation::{Animation, AnimationState},
+    audio::{AudioEvent, Emitter},
+    components::*,
+    edit::EnabledColliders,
+    particles::{ParticleEvent, ParticleKind},
+    touch::TouchControls,
+    CurrentLevel, FontHandle, GameKind, GameMode, LevelInfo, PauseState, HOVERED_BUTTON, LEVELS,
+    NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
 };
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::TAU,
+};
 
 use bevy_rapier2d::prelude::*;
 
@@ -16,24 +25,29 @@ impl Plugin for PlayPlugin {
         app.add_systems(
             Update,
             (
-                ignore_gravity_if_climbing,
+                update_gravity_scale,
                 detect_collision_with_environment,
                 movement,
+                breathe,
                 patrol,
+                move_platforms,
+                defeated,
                 ground_detection,
                 update_on_ground,
                 check_lost_condition,
                 spawn_complete_wall_collision,
-                update_level_selection,
                 spawn_ground_sensor,
                 button_system,
-                camera_fit_inside_current_level,
                 spawn_complete_wall_collision,
             )
-                .run_if(in_state(GameMode::Play)),
+                .run_if(in_state(GameMode::Play))
+                .run_if(in_state(PauseState::Running)),
         )
+        .add_event::<RestartPlaythrough>()
+        .add_systems(Update, restart.run_if(in_state(GameMode::Play)))
         .add_systems(OnEnter(GameMode::Play), setup_play_mode)
         .add_systems(OnExit(GameMode::Play), exit_mode)
+        .add_systems(OnEnter(GameMode::Lost), hurt_player)
         .add_systems(Update, freeze.run_if(not(in_state(GameMode::Play))));
     }
 }
@@ -51,6 +65,14 @@ fn exit_mode(
     player.single_mut().linvel = Vec2::ZERO;
 }
 
+// the hurt clip keeps playing under the lose screen
+fn hurt_player(mut player: Query<&mut Animation, With<Player>>) {
+    for mut animation in &mut player {
+        animation.state = AnimationState::Hurt;
+        animation.speed = 1.0;
+    }
+}
+
 fn freeze(mut moving: Query<&mut Velocity>) {
     for mut velocity in &mut moving {
         if velocity.is_changed() {
@@ -59,65 +81,127 @@ fn freeze(mut moving: Query<&mut Velocity>) {
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn movement(
     input: Res<Input<KeyCode>>,
+    touch: Res<TouchControls>,
     mut query: Query<
         (
+            Entity,
             &mut Velocity,
             &mut Climber,
+            &Swimmer,
             &GroundDetection,
             &mut TextureAtlasSprite,
+            &mut Animation,
         ),
         With<Player>,
     >,
     time: Res<Time>,
     mut audio_events: EventWriter<AudioEvent>,
+    mut particle_events: EventWriter<ParticleEvent>,
 ) {
-    for (mut velocity, mut climber, ground_detection, mut atlas) in &mut query {
-        let right = if input.pressed(KeyCode::D) { 1. } else { 0. };
-        let left = if input.pressed(KeyCode::A) { 1. } else { 0. };
+    for (entity, mut velocity, mut climber, swimmer, ground_detection, mut atlas, mut animation) in
+        &mut query
+    {
+        let right = if input.pressed(KeyCode::D) || touch.right() {
+            1.
+        } else {
+            0.
+        };
+        let left = if input.pressed(KeyCode::A) || touch.left() {
+            1.
+        } else {
+            0.
+        };
 
         velocity.linvel.x = (right - left) * 200.;
 
-        if velocity.linvel.x != 0.0 {
-            atlas.index = ((time.elapsed_seconds() * 15.0).floor() as usize) % 6 + 7;
-        }
         if velocity.linvel.x < 0.0 {
             atlas.flip_x = true;
         }
         if velocity.linvel.x > 0.0 {
             atlas.flip_x = false;
         }
-        if velocity.linvel.x == 0.0 {
-            atlas.index = ((time.elapsed_seconds() * 5.0).floor() as usize) % 4;
-        }
 
         if climber.intersecting_climbables.is_empty() {
             climber.climbing = false;
-        } else if input.just_pressed(KeyCode::W) || input.just_pressed(KeyCode::S) {
+        } else if input.just_pressed(KeyCode::W)
+            || input.just_pressed(KeyCode::S)
+            || touch.up()
+            || touch.down()
+        {
             climber.climbing = true;
         }
 
         if climber.climbing {
-            let up = if input.pressed(KeyCode::W) { 1. } else { 0. };
-            let down = if input.pressed(KeyCode::S) { 1. } else { 0. };
+            let up = if input.pressed(KeyCode::W) || touch.up() {
+                1.
+            } else {
+                0.
+            };
+            let down = if input.pressed(KeyCode::S) || touch.down() {
+                1.
+            } else {
+                0.
+            };
 
             velocity.linvel.y = (up - down) * 200.;
-            if velocity.linvel.y != 0.0 {
-                atlas.index = ((time.elapsed_seconds() * 5.0).floor() as usize) % 4 + 14;
+        } else if swimmer.swimming() {
+            let up = if input.pressed(KeyCode::W)
+                || input.pressed(KeyCode::Space)
+                || touch.up()
+                || touch.jump_pressed
+            {
+                1.
+            } else {
+                0.
+            };
+            let down = if input.pressed(KeyCode::S) || touch.down() {
+                1.
+            } else {
+                0.
+            };
+
+            velocity.linvel.x *= 0.6;
+            if up - down != 0. {
+                velocity.linvel.y = (up - down) * 150.;
+            } else {
+                // sink slowly
+                velocity.linvel.y = velocity.linvel.y.max(-80.);
             }
-        } else if velocity.linvel.y > 10.0 {
-            atlas.index = 35;
-        } else if velocity.linvel.y < -10.0 {
-            atlas.index = 36;
         }
 
-        if input.just_pressed(KeyCode::Space)
+        animation.state = if climber.climbing {
+            AnimationState::Climb
+        } else if !swimmer.swimming() && velocity.linvel.y > 10.0 {
+            AnimationState::Jump
+        } else if !swimmer.swimming() && velocity.linvel.y < -10.0 {
+            AnimationState::Fall
+        } else if velocity.linvel.x != 0.0 {
+            AnimationState::Run
+        } else {
+            AnimationState::Idle
+        };
+        // hold on to the ladder without moving
+        animation.speed = if climber.climbing && velocity.linvel.y == 0.0 {
+            0.0
+        } else {
+            1.0
+        };
+        // no footsteps in the air or in water
+        animation.muted = !climber.climbing && (!ground_detection.on_ground || swimmer.swimming());
+
+        if (input.just_pressed(KeyCode::Space) || touch.jump_just_pressed)
             && (ground_detection.on_ground
                 || time.elapsed_seconds() - ground_detection.left_ground < 0.18
                 || climber.climbing)
         {
-            audio_events.send(AudioEvent::Jump);
+            audio_events.send(AudioEvent::new("jump").with_entity(entity));
+            particle_events.send(ParticleEvent::new(
+                ParticleKind::Dust,
+                Emitter::Entity(entity),
+            ));
             velocity.linvel.y = 500.;
             climber.climbing = false;
         }
@@ -152,8 +236,12 @@ fn spawn_complete_wall_collision(
 
     wall_query.for_each(|(&grid_coords, parent)| {
         if let Ok(grandparent) = parent_query.get(parent.get()) {
+            let level_iid = level_query
+                .get(grandparent.get())
+                .map(|(_, level_iid)| level_iid.to_string())
+                .unwrap_or_default();
             if matches!(game_kind.get(), GameKind::Platformer)
-                || enabled.coords.contains(&grid_coords)
+                || enabled.contains(&level_iid, &grid_coords)
             {
                 level_to_wall_locations
                     .entry(grandparent.get())
@@ -273,14 +361,20 @@ fn spawn_complete_wall_collision(
 
 #[allow(clippy::too_many_arguments)]
 fn detect_collision_with_environment(
+    mut commands: Commands,
     mut climbers: Query<&mut Climber>,
     climbables: Query<Entity, With<Climbable>>,
+    mut swimmers: Query<&mut Swimmer>,
+    hazards: Query<&Hazard>,
     mut collisions: EventReader<CollisionEvent>,
-    player: Query<&Player>,
+    mut player: Query<&mut Velocity, With<Player>>,
     chests: Query<&Chest>,
-    ennemy: Query<&Patrol>,
+    ennemy: Query<&Enemy, Without<Defeated>>,
+    ground_sensors: Query<&GroundSensor>,
+    rapier_context: Res<RapierContext>,
     mut next_state: ResMut<NextState<GameMode>>,
     mut audio_events: EventWriter<AudioEvent>,
+    mut particle_events: EventWriter<ParticleEvent>,
     mut playthrough: ResMut<Playthrough>,
 ) {
     for collision in collisions.read() {
@@ -296,18 +390,77 @@ fn detect_collision_with_environment(
                 {
                     climber.intersecting_climbables.insert(climbable);
                 };
-                if (player.contains(*collider_a) && chests.contains(*collider_b))
-                    || (player.contains(*collider_b) && chests.contains(*collider_a))
-                {
-                    audio_events.send(AudioEvent::Win);
+                let chest = if player.contains(*collider_a) && chests.contains(*collider_b) {
+                    Some(*collider_b)
+                } else if player.contains(*collider_b) && chests.contains(*collider_a) {
+                    Some(*collider_a)
+                } else {
+                    None
+                };
+                if let Some(chest) = chest {
+                    audio_events.send(AudioEvent::new("win"));
+                    particle_events.send(ParticleEvent::new(
+                        ParticleKind::Confetti,
+                        Emitter::Entity(chest),
+                    ));
                     next_state.set(GameMode::Won);
                 }
-                if (player.contains(*collider_a) && ennemy.contains(*collider_b))
-                    || (player.contains(*collider_b) && ennemy.contains(*collider_a))
+                let player_and_mob = if player.contains(*collider_a) && ennemy.contains(*collider_b)
                 {
-                    audio_events.send(AudioEvent::Eagle);
-                    playthrough.enemy_hit = true;
-                    next_state.set(GameMode::Lost);
+                    Some((*collider_a, *collider_b))
+                } else if player.contains(*collider_b) && ennemy.contains(*collider_a) {
+                    Some((*collider_b, *collider_a))
+                } else {
+                    None
+                };
+                if let Some((player_entity, mob_entity)) = player_and_mob {
+                    if stomped(&rapier_context, &ground_sensors, player_entity, mob_entity) {
+                        audio_events.send(AudioEvent::new("stomp").with_entity(player_entity));
+                        commands
+                            .entity(mob_entity)
+                            .insert(Defeated::default())
+                            .remove::<Collider>();
+                        if let Ok(mut velocity) = player.get_mut(player_entity) {
+                            velocity.linvel.y = 300.;
+                        }
+                    } else {
+                        audio_events.send(AudioEvent::new("eagle").with_entity(mob_entity));
+                        playthrough.enemy_hit = true;
+                        next_state.set(GameMode::Lost);
+                    }
+                }
+                let player_and_hazard = if player.contains(*collider_a) {
+                    hazards
+                        .get(*collider_b)
+                        .ok()
+                        .map(|hazard| (*collider_a, *collider_b, *hazard))
+                } else if player.contains(*collider_b) {
+                    hazards
+                        .get(*collider_a)
+                        .ok()
+                        .map(|hazard| (*collider_b, *collider_a, *hazard))
+                } else {
+                    None
+                };
+                match player_and_hazard {
+                    Some((player_entity, water, Hazard::Water)) => {
+                        if let Ok(mut swimmer) = swimmers.get_mut(player_entity) {
+                            if !swimmer.swimming() {
+                                audio_events
+                                    .send(AudioEvent::new("splash").with_entity(player_entity));
+                            }
+                            swimmer.intersecting_water.insert(water);
+                        }
+                    }
+                    Some((_, _, hazard)) => {
+                        audio_events.send(match hazard {
+                            Hazard::Lava => AudioEvent::new("lava"),
+                            _ => AudioEvent::new("spikes"),
+                        });
+                        playthrough.hazard = Some(hazard);
+                        next_state.set(GameMode::Lost);
+                    }
+                    None => (),
                 }
             }
             CollisionEvent::Stopped(collider_a, collider_b, _) => {
@@ -322,165 +475,210 @@ fn detect_collision_with_environment(
                 {
                     climber.intersecting_climbables.remove(&climbable);
                 }
+
+                if let (Ok(mut swimmer), Ok(Hazard::Water)) =
+                    (swimmers.get_mut(*collider_a), hazards.get(*collider_b))
+                {
+                    swimmer.intersecting_water.remove(collider_b);
+                }
+
+                if let (Ok(mut swimmer), Ok(Hazard::Water)) =
+                    (swimmers.get_mut(*collider_b), hazards.get(*collider_a))
+                {
+                    swimmer.intersecting_water.remove(collider_a);
+                }
             }
         }
     }
 }
 
-fn ignore_gravity_if_climbing(mut query: Query<(&Climber, &mut GravityScale), Changed<Climber>>) {
-    for (climber, mut gravity_scale) in &mut query {
+// The player lands on a mob if the contact normal points down from the player, or if the
+// ground sensor under their feet is touching it.
+fn stomped(
+    rapier_context: &RapierContext,
+    ground_sensors: &Query<&GroundSensor>,
+    player: Entity,
+    mob: Entity,
+) -> bool {
+    let on_ground_sensor = ground_sensors.iter().any(|sensor| {
+        sensor.ground_detection_entity == player
+            && sensor.intersecting_ground_entities.contains(&mob)
+    });
+    let from_above = rapier_context
+        .contact_pair(player, mob)
+        .is_some_and(|contact_pair| {
+            contact_pair.manifolds().any(|manifold| {
+                // the normal goes from the first collider of the pair to the second one
+                let normal = if contact_pair.collider1() == player {
+                    manifold.normal()
+                } else {
+                    -manifold.normal()
+                };
+                normal.y < -0.7
+            })
+        });
+    on_ground_sensor || from_above
+}
+
+#[allow(clippy::type_complexity)]
+fn update_gravity_scale(
+    mut query: Query<
+        (&Climber, &Swimmer, &mut GravityScale),
+        Or<(Changed<Climber>, Changed<Swimmer>)>,
+    >,
+) {
+    for (climber, swimmer, mut gravity_scale) in &mut query {
         if climber.climbing {
             gravity_scale.0 = 0.0;
+        } else if swimmer.swimming() {
+            gravity_scale.0 = 0.3;
         } else {
             gravity_scale.0 = 1.0;
         }
     }
 }
 
-fn patrol(
-    mut query: Query<(
-        &mut Transform,
-        &mut Velocity,
-        &mut Patrol,
-        &mut TextureAtlasSprite,
-    )>,
+fn breathe(
+    mut swimmers: Query<&mut Swimmer>,
     time: Res<Time>,
+    mut next_state: ResMut<NextState<GameMode>>,
+    mut playthrough: ResMut<Playthrough>,
+    mut audio_events: EventWriter<AudioEvent>,
 ) {
-    for (mut transform, mut velocity, mut patrol, mut sprite) in &mut query {
-        sprite.index = ((time.elapsed_seconds() * 5.0).floor() as usize) % 4;
-        if patrol.points.len() <= 1 {
+    for mut swimmer in &mut swimmers {
+        if !swimmer.swimming() {
+            if swimmer.breath.elapsed_secs() > 0. {
+                swimmer.breath.reset();
+            }
             continue;
         }
+        if swimmer.breath.tick(time.delta()).just_finished() {
+            audio_events.send(AudioEvent::new("splash"));
+            playthrough.hazard = Some(Hazard::Water);
+            next_state.set(GameMode::Lost);
+        }
+    }
+}
 
-        let mut new_velocity =
-            (patrol.points[patrol.index] - transform.translation.truncate()).normalize() * 75.;
+const FLYING_FREQUENCY: f32 = 3.;
 
-        if new_velocity.dot(velocity.linvel) < 0. {
-            if patrol.index == 0 {
-                patrol.forward = true;
-            } else if patrol.index == patrol.points.len() - 1 {
-                patrol.forward = false;
+#[allow(clippy::type_complexity)]
+fn patrol(
+    mut query: Query<
+        (&mut Transform, &mut Patrol, &mut MobBehaviour),
+        (Without<Player>, Without<Defeated>),
+    >,
+    player: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+) {
+    let player = player
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+
+    for (mut transform, mut patrol, mut behaviour) in &mut query {
+        if let MobBehaviour::Chase { radius, chasing } = behaviour.as_mut() {
+            let to_player = player.map(|player| player - transform.translation.truncate());
+            *chasing = to_player.is_some_and(|to_player| to_player.length() < *radius);
+            if let Some(to_player) = to_player.filter(|_| *chasing) {
+                let step = to_player.clamp_length_max(patrol.speed * time.delta_seconds());
+                transform.translation += step.extend(0.);
+                // resume the patrol from wherever the chase ended
+                patrol.position = transform.translation.truncate();
+                continue;
             }
+        }
 
-            transform.translation.x = patrol.points[patrol.index].x;
-            transform.translation.y = patrol.points[patrol.index].y;
-
-            if patrol.forward {
-                patrol.index += 1;
-            } else {
-                patrol.index -= 1;
+        if let MobBehaviour::Wait { pause } = behaviour.as_ref() {
+            if patrol.pause.duration() != *pause {
+                patrol.pause.set_duration(*pause);
+                // only wait once the end of the path is reached
+                patrol.pause.tick(*pause);
             }
-
-            new_velocity =
-                (patrol.points[patrol.index] - transform.translation.truncate()).normalize() * 75.;
         }
 
-        velocity.linvel = new_velocity;
+        patrol.advance(time.delta());
+
+        let mut position = patrol.position;
+        if let MobBehaviour::Flying { amplitude, phase } = behaviour.as_mut() {
+            *phase = (*phase + time.delta_seconds() * FLYING_FREQUENCY) % TAU;
+            position.y += *amplitude * phase.sin();
+        }
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
     }
 }
 
-#[allow(clippy::type_complexity)]
-pub fn camera_fit_inside_current_level(
-    mut camera_query: Query<
-        (
-            &mut bevy::render::camera::OrthographicProjection,
-            &mut Transform,
-        ),
-        Without<Player>,
-    >,
-    player_query: Query<&Transform, With<Player>>,
-    level_query: Query<(&Transform, &LevelIid), (Without<OrthographicProjection>, Without<Player>)>,
-    ldtk_projects: Query<&Handle<LdtkProject>>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
-    window: Query<&Window>,
+// Platforms carry whatever has its ground sensor on them or rests on top of them, like props.
+fn move_platforms(
+    mut platforms: Query<(Entity, &mut Transform, &mut Patrol), With<MovingPlatform>>,
+    mut riders: Query<(&mut Transform, &RigidBody), Without<MovingPlatform>>,
+    ground_sensors: Query<&GroundSensor>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
 ) {
-    if let Ok(Transform {
-        translation: player_translation,
-        ..
-    }) = player_query.get_single()
-    {
-        let window_aspect_ratio = window
-            .get_single()
-            .map(|w| w.width() / w.height())
-            .unwrap_or(1.);
-        let player_translation = *player_translation;
-
-        let (mut orthographic_projection, mut camera_transform) = camera_query.single_mut();
-
-        for (level_transform, level_iid) in &level_query {
-            let ldtk_project = ldtk_project_assets
-                .get(ldtk_projects.single())
-                .expect("Project should be loaded if level has spawned");
-
-            let level = ldtk_project
-                .get_raw_level_by_iid(&level_iid.to_string())
-                .expect("Spawned level should exist in LDtk project");
-
-            let level_ratio = level.px_wid as f32 / level.px_hei as f32;
-            orthographic_projection.viewport_origin = Vec2::ZERO;
-            if level_ratio > window_aspect_ratio {
-                // level is wider than the screen
-                let height = (level.px_hei as f32 / 9.).round() * 9.;
-                let width = height * window_aspect_ratio;
-                orthographic_projection.scaling_mode = bevy::render::camera::ScalingMode::Fixed {
-                    width,
-                    height: height - 4.0,
-                };
-                camera_transform.translation.x =
-                    (player_translation.x - level_transform.translation.x - width / 2.)
-                        .clamp(0., level.px_wid as f32 - width);
-                camera_transform.translation.y = 0.;
+    for (platform, mut transform, mut patrol) in &mut platforms {
+        let previous = patrol.position;
+        patrol.advance(time.delta());
+        let delta = patrol.position - previous;
+
+        transform.translation.x = patrol.position.x;
+        transform.translation.y = patrol.position.y;
+
+        let mut carried: HashSet<Entity> = ground_sensors
+            .iter()
+            .filter(|sensor| sensor.intersecting_ground_entities.contains(&platform))
+            .map(|sensor| sensor.ground_detection_entity)
+            .collect();
+        for contact_pair in rapier_context.contacts_with(platform) {
+            let rider = if contact_pair.collider1() == platform {
+                contact_pair.collider2()
             } else {
-                // level is taller than the screen
-                let width = (level.px_wid as f32 / 16.).round() * 16.;
-                let height = width / window_aspect_ratio;
-                orthographic_projection.scaling_mode =
-                    bevy::render::camera::ScalingMode::Fixed { width, height };
-                camera_transform.translation.y =
-                    (player_translation.y - level_transform.translation.y - height / 2.)
-                        .clamp(0., level.px_hei as f32 - height);
-                camera_transform.translation.x = 0.;
+                contact_pair.collider1()
+            };
+            let on_top = contact_pair.manifolds().any(|manifold| {
+                // the normal goes from the first collider of the pair to the second one
+                let normal = if contact_pair.collider1() == rider {
+                    manifold.normal()
+                } else {
+                    -manifold.normal()
+                };
+                normal.y < -0.7
+            });
+            if contact_pair.has_any_active_contacts() && on_top {
+                carried.insert(rider);
             }
+        }
 
-            camera_transform.translation.x += level_transform.translation.x;
-            camera_transform.translation.y += level_transform.translation.y;
+        for entity in carried {
+            if let Ok((mut rider, RigidBody::Dynamic)) = riders.get_mut(entity) {
+                rider.translation += delta.extend(0.);
+            }
         }
     }
 }
 
-fn update_level_selection(
-    level_query: Query<(&LevelIid, &Transform), Without<Player>>,
-    player_query: Query<&Transform, With<Player>>,
-    mut level_selection: ResMut<LevelSelection>,
-    ldtk_projects: Query<&Handle<LdtkProject>>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
+fn defeated(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut Defeated,
+        &mut Transform,
+        &mut TextureAtlasSprite,
+        &mut Animation,
+    )>,
+    time: Res<Time>,
 ) {
-    for (level_iid, level_transform) in &level_query {
-        let ldtk_project = ldtk_project_assets
-            .get(ldtk_projects.single())
-            .expect("Project should be loaded if level has spawned");
-
-        let level = ldtk_project
-            .get_raw_level_by_iid(&level_iid.to_string())
-            .expect("Spawned level should exist in LDtk project");
-
-        let level_bounds = Rect {
-            min: Vec2::new(level_transform.translation.x, level_transform.translation.y),
-            max: Vec2::new(
-                level_transform.translation.x + level.px_wid as f32,
-                level_transform.translation.y + level.px_hei as f32,
-            ),
-        };
-
-        for player_transform in &player_query {
-            if player_transform.translation.x < level_bounds.max.x
-                && player_transform.translation.x > level_bounds.min.x
-                && player_transform.translation.y < level_bounds.max.y
-                && player_transform.translation.y > level_bounds.min.y
-                && !level_selection.is_match(&LevelIndices::default(), level)
-            {
-                *level_selection = LevelSelection::iid(level.iid.clone());
-            }
+    for (entity, mut defeated, mut transform, mut sprite, mut animation) in &mut query {
+        // play the hurt clip once while tumbling out of the sky
+        defeated.timer.tick(time.delta());
+        animation.state = AnimationState::Hurt;
+        sprite.flip_y = true;
+        sprite.color.set_a(defeated.timer.percent_left());
+        transform.translation.y -= 150. * time.delta_seconds();
+
+        if defeated.timer.finished() {
+            commands.entity(entity).despawn_recursive();
         }
     }
 }
@@ -576,6 +774,7 @@ fn setup_play_mode(
     colliders: Res<EnabledColliders>,
     font: Res<FontHandle>,
     game_kind: Res<State<GameKind>>,
+    level: Res<CurrentLevel>,
 ) {
     let button_style = Style {
         width: Val::Px(150.0),
@@ -595,6 +794,8 @@ fn setup_play_mode(
     for world_entity in &world_query {
         commands.entity(world_entity).insert(Respawn);
     }
+    // always start from the first room of the stage
+    commands.insert_resource(LevelSelection::index(level.0));
     rapier_config.gravity = Vec2::new(0.0, -2000.0);
 
     commands
@@ -611,6 +812,20 @@ fn setup_play_mode(
         })
         .insert(OnPlayMode)
         .with_children(|parent| {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: button_style.clone(),
+                        background_color: NORMAL_BUTTON.into(),
+                        border_color: BorderColor(HOVERED_BUTTON),
+                        ..default()
+                    },
+                    ButtonAction::Pause,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Pause", button_text_style.clone()));
+                });
+
             if matches!(game_kind.get(), GameKind::Puzzle) {
                 parent
                     .spawn((
@@ -629,7 +844,7 @@ fn setup_play_mode(
                 parent.spawn(
                     TextBundle::from_sections([
                         TextSection {
-                            value: colliders.coords.len().to_string(),
+                            value: colliders.count().to_string(),
                             style: TextStyle {
                                 font_size: 20.,
                                 color: TEXT_COLOR,
@@ -653,7 +868,7 @@ fn setup_play_mode(
             }
             parent.spawn(TextBundle::from_sections([
                 TextSection {
-                    value: "60.0".to_string(),
+                    value: format!("{:.1}", LEVELS[level.0].time_limit),
                     style: TextStyle {
                         font_size: 20.,
                         color: Color::GREEN,
@@ -671,31 +886,60 @@ fn setup_play_mode(
             ]));
         });
 
-    commands.insert_resource(Playthrough {
-        timer: Timer::from_seconds(60.0, TimerMode::Once),
-        lost_chest: false,
-        lost_player: false,
-        enemy_hit: false,
-    });
+    commands.insert_resource(Playthrough::new(&LEVELS[level.0]));
 }
 
 #[derive(Resource)]
 pub struct Playthrough {
     pub timer: Timer,
     pub lost_player: bool,
-    pub lost_chest: bool,
+    pub lost_prop: Option<String>,
+    pub hazard: Option<Hazard>,
     pub enemy_hit: bool,
 }
 
+impl Playthrough {
+    pub fn new(level: &LevelInfo) -> Self {
+        Playthrough {
+            timer: Timer::from_seconds(level.time_limit, TimerMode::Once),
+            lost_prop: None,
+            hazard: None,
+            lost_player: false,
+            enemy_hit: false,
+        }
+    }
+}
+
+/// Starts the stage over without leaving play mode.
+#[derive(Event)]
+pub struct RestartPlaythrough;
+
+fn restart(
+    mut commands: Commands,
+    mut restarts: EventReader<RestartPlaythrough>,
+    world_query: Query<Entity, With<Handle<LdtkProject>>>,
+    level: Res<CurrentLevel>,
+) {
+    if restarts.read().count() == 0 {
+        return;
+    }
+    for world_entity in &world_query {
+        commands.entity(world_entity).insert(Respawn);
+    }
+    commands.insert_resource(LevelSelection::index(level.0));
+    commands.insert_resource(Playthrough::new(&LEVELS[level.0]));
+}
+
 #[derive(Component)]
 enum ButtonAction {
     Edit,
+    Pause,
 }
 
 #[allow(clippy::too_many_arguments)]
 fn check_lost_condition(
     mut next: ResMut<NextState<GameMode>>,
-    chest: Query<&Transform, With<Chest>>,
+    props: Query<(&GlobalTransform, &Prop)>,
     player: Query<&Transform, With<Player>>,
     respawn: Query<&Respawn>,
     time: Res<Time>,
@@ -706,15 +950,17 @@ fn check_lost_condition(
     if respawn.iter().count() > 0 {
         return;
     }
-    let transform = chest.single();
-    if transform.translation.y < 0. {
-        audio_events.send(AudioEvent::Fall);
-        next.set(GameMode::Lost);
-        playthrough.lost_chest = true;
+    for (transform, prop) in &props {
+        if prop.required && transform.translation().y < 0. && playthrough.lost_prop.is_none() {
+            audio_events.send(AudioEvent::new("fall"));
+            next.set(GameMode::Lost);
+            playthrough.lost_prop = Some(prop.name.clone());
+        }
     }
+    // the player is worldly, so its transform is already in world space
     let transform = player.single();
     if transform.translation.y < 0. {
-        audio_events.send(AudioEvent::Fall);
+        audio_events.send(AudioEvent::new("fall").with_position(transform.translation.truncate()));
         next.set(GameMode::Lost);
         playthrough.lost_player = true;
     }
@@ -724,9 +970,11 @@ fn check_lost_condition(
     for mut text in &mut text {
         if text.sections[0].style.color != TEXT_COLOR {
             text.sections[0].value = format!("{:.1}", playthrough.timer.remaining_secs());
-            if playthrough.timer.remaining_secs() < 20.0 {
-                text.sections[0].style.color = Color::RED;
-            }
+            text.sections[0].style.color = if playthrough.timer.remaining_secs() < 20.0 {
+                Color::RED
+            } else {
+                Color::GREEN
+            };
         }
     }
 }
@@ -738,14 +986,16 @@ fn button_system(
         (Changed<Interaction>, With<Button>),
     >,
     mut next_state: ResMut<NextState<GameMode>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
     mut audio_events: EventWriter<AudioEvent>,
 ) {
     for (interaction, mut color, button) in &mut interaction_query {
         *color = match *interaction {
             Interaction::Pressed => {
-                audio_events.send(AudioEvent::Click);
+                audio_events.send(AudioEvent::new("click"));
                 match button {
                     ButtonAction::Edit => next_state.set(GameMode::Edit),
+                    ButtonAction::Pause => next_pause_state.set(PauseState::Paused),
                 }
                 PRESSED_BUTTON.into()
             }
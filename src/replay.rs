@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use bevy::{
+    app::SubApp,
+    prelude::*,
+    render::{render_resource::Shader, RenderApp},
+    time::TimeUpdateStrategy,
+    utils::Instant,
+};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_embedded_assets::EmbeddedAssetPlugin;
+use bevy_rapier2d::prelude::*;
+use thiserror::Error;
+
+use crate::{
+    audio::AudioEvent,
+    components::{ComponentsPlugin, Player},
+    edit::EnabledColliders,
+    particles::ParticleEvent,
+    play::{PlayPlugin, Playthrough, RestartPlaythrough},
+    rooms::{stage_platforms, RoomsPlugin},
+    touch::TouchControls,
+    CurrentLevel, FontHandle, GameKind, GameMode, PauseState, LDTK_PROJECT,
+};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replay>()
+            .add_systems(OnEnter(GameMode::Play), start_recording)
+            .add_systems(
+                Update,
+                (restart_recording, record)
+                    .chain()
+                    .run_if(in_state(GameMode::Play))
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+// keys the player moves with, a frame's held keys are stored as bits in this order
+const KEYS: [KeyCode; 5] = [
+    KeyCode::A,
+    KeyCode::D,
+    KeyCode::W,
+    KeyCode::S,
+    KeyCode::Space,
+];
+
+/// Inputs of the current run, one entry per frame with the frame's duration and held keys.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub frames: Vec<(Duration, u8)>,
+}
+
+impl Replay {
+    /// Text form of the frames, like `16667.0,16702.2` for each frame's duration in microseconds
+    /// and held keys.
+    pub fn code(&self) -> String {
+        let frames: Vec<_> = self
+            .frames
+            .iter()
+            .map(|(delta, keys)| format!("{}.{}", delta.as_micros(), keys))
+            .collect();
+        frames.join(",")
+    }
+
+    /// Reads back the frames from [`Replay::code`].
+    pub fn from_code(code: &str) -> Option<Self> {
+        let frames = code
+            .split(',')
+            .filter(|frame| !frame.is_empty())
+            .map(|frame| {
+                let (delta, keys) = frame.split_once('.')?;
+                Some((
+                    Duration::from_micros(delta.parse().ok()?),
+                    keys.parse().ok()?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        Some(Replay { frames })
+    }
+}
+
+fn start_recording(mut replay: ResMut<Replay>) {
+    replay.frames.clear();
+}
+
+fn restart_recording(mut restarts: EventReader<RestartPlaythrough>, mut replay: ResMut<Replay>) {
+    if restarts.read().count() > 0 {
+        replay.frames.clear();
+    }
+}
+
+fn record(
+    mut replay: ResMut<Replay>,
+    input: Res<Input<KeyCode>>,
+    touch: Res<TouchControls>,
+    time: Res<Time>,
+) {
+    // the on-screen controls stand in for the same keys
+    let touched = [
+        touch.left(),
+        touch.right(),
+        touch.up(),
+        touch.down(),
+        touch.jump_pressed,
+    ];
+    let keys = KEYS
+        .iter()
+        .zip(touched)
+        .enumerate()
+        .filter(|(_, (key, touched))| input.pressed(**key) || *touched)
+        .fold(0, |keys, (bit, _)| keys | 1 << bit);
+    replay.frames.push((time.delta(), keys));
+}
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("the level didn't load")]
+    NotLoaded,
+    #[error("the run was lost")]
+    Lost,
+    #[error("the run ended before reaching the chest")]
+    Unfinished,
+}
+
+/// A replayed run that reached the chest.
+#[derive(Clone, Copy, Debug)]
+pub struct ReplayedRun {
+    pub time: f32,
+    /// Enabled walls and the moving platforms of the whole stage.
+    pub colliders: usize,
+}
+
+// assets load on other threads, so waiting for the level is in real time
+const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Plays a run back without a window or sound: spawns the level with the given colliders, then
+/// holds the recorded keys frame by frame until the chest is reached.
+pub fn play_back(
+    level: usize,
+    colliders: EnabledColliders,
+    replay: &Replay,
+) -> Result<ReplayedRun, ReplayError> {
+    let walls = colliders.count();
+    let mut app = headless_app(level, colliders);
+
+    let loading = Instant::now();
+    while app
+        .world
+        .query_filtered::<(), With<Player>>()
+        .iter(&app.world)
+        .next()
+        .is_none()
+    {
+        if loading.elapsed() > LOAD_TIMEOUT {
+            return Err(ReplayError::NotLoaded);
+        }
+        app.update();
+    }
+    let platforms = {
+        let world = app.world.query::<&Handle<LdtkProject>>().single(&app.world);
+        let ldtk_project = app.world.resource::<Assets<LdtkProject>>().get(world);
+        ldtk_project.map_or(0, |ldtk_project| stage_platforms(ldtk_project, level))
+    };
+
+    app.world
+        .resource_mut::<NextState<GameMode>>()
+        .set(GameMode::Play);
+    for (delta, keys) in &replay.frames {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(*delta));
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        input.clear();
+        for (bit, key) in KEYS.iter().enumerate() {
+            if keys & 1 << bit != 0 {
+                input.press(*key);
+            } else {
+                input.release(*key);
+            }
+        }
+
+        app.update();
+
+        match app.world.resource::<NextState<GameMode>>().0 {
+            Some(GameMode::Won) => {
+                return Ok(ReplayedRun {
+                    time: app.world.resource::<Playthrough>().timer.elapsed_secs(),
+                    colliders: walls + platforms,
+                })
+            }
+            Some(GameMode::Lost) => return Err(ReplayError::Lost),
+            _ => (),
+        }
+    }
+    Err(ReplayError::Unfinished)
+}
+
+// Only the plugins the gameplay depends on, the level sits in the menu until the replay starts.
+fn headless_app(level: usize, colliders: EnabledColliders) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        EmbeddedAssetPlugin {
+            mode: bevy_embedded_assets::PluginMode::ReplaceDefault,
+        },
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        ImagePlugin::default_nearest(),
+    ))
+    // the tilemap and sprite rendering isn't there, only their assets
+    .init_asset::<Shader>()
+    .init_asset::<TextureAtlas>()
+    .init_resource::<ClearColor>();
+    // the tilemap plugin adds systems to the render app, so it gets a stand-in that's dropped
+    // before anything runs
+    app.insert_sub_app(RenderApp, SubApp::new(App::empty(), |_, _| {}));
+    app.add_plugins(LdtkPlugin);
+    app.remove_sub_app(RenderApp);
+    app.add_plugins((
+        RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0),
+        ComponentsPlugin,
+        PlayPlugin,
+        RoomsPlugin,
+    ))
+    .insert_resource(RapierConfiguration {
+        gravity: Vec2::new(0.0, 0.0),
+        ..Default::default()
+    })
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO))
+    .init_resource::<Input<KeyCode>>()
+    .init_resource::<TouchControls>()
+    .add_event::<AudioEvent>()
+    .add_event::<ParticleEvent>()
+    .add_state::<GameMode>()
+    .add_state::<GameKind>()
+    .add_state::<PauseState>()
+    .insert_resource(NextState(Some(GameKind::Puzzle)))
+    .insert_resource(FontHandle(Handle::default()))
+    .insert_resource(colliders)
+    .insert_resource(LevelSelection::index(level))
+    .insert_resource(CurrentLevel(level));
+
+    // the image loader is only registered once the plugins finish
+    app.finish();
+    app.cleanup();
+    let world = app.world.resource::<AssetServer>().load(LDTK_PROJECT);
+    app.world.spawn(LdtkWorldBundle {
+        ldtk_handle: world,
+        ..Default::default()
+    });
+    app
+}
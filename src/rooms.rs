@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    components::{Entrance, Exit, Player},
+    GameMode, PauseState,
+};
+
+pub struct RoomsPlugin;
+
+impl Plugin for RoomsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (use_exits, room_transition)
+                .run_if(in_state(GameMode::Play))
+                .run_if(in_state(PauseState::Running)),
+        )
+        .add_systems(OnExit(GameMode::Play), end_transition);
+    }
+}
+
+/// IID of the room a stage starts in.
+pub fn start_room(ldtk_project: &LdtkProject, level: usize) -> Option<String> {
+    ldtk_project
+        .iter_raw_levels()
+        .nth(level)
+        .map(|level| level.iid.clone())
+}
+
+/// IIDs of all the rooms reachable through exits from the start room, the start room first.
+pub fn stage_rooms(ldtk_project: &LdtkProject, start_room: &str) -> Vec<String> {
+    let mut rooms = vec![start_room.to_string()];
+    let mut next = 0;
+    while let Some(room) = rooms.get(next).cloned() {
+        next += 1;
+        let Some(level) = ldtk_project.get_raw_level_by_iid(&room) else {
+            continue;
+        };
+        for layer in level.layer_instances.iter().flatten() {
+            for entity_instance in &layer.entity_instances {
+                if entity_instance.identifier != "Exit" {
+                    continue;
+                }
+                if let Ok(to) = entity_instance.get_entity_ref_field("to") {
+                    if !rooms.contains(&to.level_iid) {
+                        rooms.push(to.level_iid.clone());
+                    }
+                }
+            }
+        }
+    }
+    rooms
+}
+
+/// Number of moving platforms over all the rooms of a stage.
+pub fn stage_platforms(ldtk_project: &LdtkProject, level: usize) -> usize {
+    let Some(start) = start_room(ldtk_project, level) else {
+        return 0;
+    };
+    stage_rooms(ldtk_project, &start)
+        .iter()
+        .filter_map(|room| ldtk_project.get_raw_level_by_iid(room))
+        .flat_map(|level| level.layer_instances.iter().flatten())
+        .flat_map(|layer| &layer.entity_instances)
+        .filter(|entity_instance| entity_instance.identifier == "MovingPlatform")
+        .count()
+}
+
+/// The room after the one currently selected, looping back to the start room.
+pub fn next_room(
+    ldtk_project: &LdtkProject,
+    level_selection: &LevelSelection,
+    level: usize,
+) -> Option<LevelSelection> {
+    let rooms = stage_rooms(ldtk_project, &start_room(ldtk_project, level)?);
+    let current = rooms
+        .iter()
+        .position(|room| {
+            ldtk_project
+                .iter_raw_levels()
+                .enumerate()
+                .any(|(index, level)| {
+                    level.iid == *room
+                        && level_selection.is_match(&LevelIndices::in_root(index), level)
+                })
+        })
+        .unwrap_or(0);
+    Some(LevelSelection::iid(
+        rooms[(current + 1) % rooms.len()].clone(),
+    ))
+}
+
+#[derive(Resource)]
+struct RoomTransition {
+    exit: Exit,
+    timer: Timer,
+    switched: bool,
+    arrived: bool,
+}
+
+#[derive(Component)]
+struct RoomFade;
+
+fn use_exits(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionEvent>,
+    player: Query<(), With<Player>>,
+    exits: Query<&Exit>,
+    transition: Option<Res<RoomTransition>>,
+) {
+    for collision in collisions.read() {
+        let CollisionEvent::Started(collider_a, collider_b, _) = collision else {
+            continue;
+        };
+        let exit = if player.contains(*collider_a) {
+            exits.get(*collider_b)
+        } else if player.contains(*collider_b) {
+            exits.get(*collider_a)
+        } else {
+            continue;
+        };
+        let Ok(exit) = exit else {
+            continue;
+        };
+        if transition.is_some() {
+            continue;
+        }
+
+        commands.insert_resource(RoomTransition {
+            exit: exit.clone(),
+            timer: Timer::from_seconds(0.6, TimerMode::Once),
+            switched: false,
+            arrived: false,
+        });
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.0).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            RoomFade,
+        ));
+        return;
+    }
+}
+
+fn room_transition(
+    mut commands: Commands,
+    transition: Option<ResMut<RoomTransition>>,
+    mut level_selection: ResMut<LevelSelection>,
+    entrances: Query<(&EntityIid, Ref<GlobalTransform>), With<Entrance>>,
+    mut player: Query<(&mut Transform, &mut Velocity), With<Player>>,
+    mut fade: Query<(Entity, &mut BackgroundColor), With<RoomFade>>,
+    time: Res<Time>,
+) {
+    let Some(mut transition) = transition else {
+        return;
+    };
+
+    // hold the screen black until the next room has spawned
+    let waiting_for_room = transition.switched && !transition.arrived;
+    if !waiting_for_room {
+        transition.timer.tick(time.delta());
+    }
+    let progress = transition.timer.percent();
+
+    if progress >= 0.5 && !transition.switched {
+        *level_selection = LevelSelection::iid(transition.exit.level_iid.clone());
+        transition.switched = true;
+    }
+
+    if transition.switched && !transition.arrived {
+        // the entrance is in its level's space, and its global transform is only
+        // propagated at the end of the frame it spawned on
+        if let Some((_, entrance)) = entrances.iter().find(|(entity_iid, entrance)| {
+            entity_iid.to_string() == transition.exit.entrance_iid && !entrance.is_added()
+        }) {
+            let position = entrance.translation();
+            // the player is worldly, so its transform is already in world space
+            for (mut transform, mut velocity) in &mut player {
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+                velocity.linvel = Vec2::ZERO;
+            }
+            transition.arrived = true;
+        }
+    }
+
+    // fade to black, then back in once in the next room
+    let alpha = 1.0 - (progress * 2.0 - 1.0).abs();
+    for (entity, mut color) in &mut fade {
+        color.0.set_a(alpha);
+        if transition.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    if transition.timer.finished() {
+        commands.remove_resource::<RoomTransition>();
+    }
+}
+
+fn end_transition(mut commands: Commands, fade: Query<Entity, With<RoomFade>>) {
+    for entity in &fade {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<RoomTransition>();
+}
@@ -0,0 +1,673 @@
+use bevy::{
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+
+use crate::{
+    audio::AudioEvent, FontHandle, GameMode, HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON,
+    TEXT_COLOR,
+};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Settings::load())
+            .init_resource::<EditingName>()
+            .add_systems(Update, apply_settings)
+            .add_systems(OnEnter(GameMode::Settings), setup)
+            .add_systems(OnExit(GameMode::Settings), exit_screen)
+            .add_systems(
+                Update,
+                (
+                    button_system,
+                    drag_sliders,
+                    type_name,
+                    update_widgets,
+                    update_name,
+                )
+                    .chain()
+                    .run_if(in_state(GameMode::Settings)),
+            );
+    }
+}
+
+/// Player settings, saved when leaving the settings screen.
+#[derive(Resource, Clone, PartialEq)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub ui_scale: f32,
+    /// Shows a split timer over all the levels when playing the platformer.
+    pub speedrun_timer: bool,
+    /// Name put on leaderboard entries.
+    pub player_name: String,
+    /// Server of the online leaderboard, empty to keep results local. Only set in the settings
+    /// file, and read at startup.
+    pub leaderboard_url: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            master_volume: 1.0,
+            sfx_volume: 1.0,
+            music_volume: 0.7,
+            fullscreen: false,
+            vsync: true,
+            ui_scale: 1.0,
+            speedrun_timer: false,
+            player_name: DEFAULT_PLAYER_NAME.to_string(),
+            leaderboard_url: String::new(),
+        }
+    }
+}
+
+const DEFAULT_PLAYER_NAME: &str = "Player";
+const MAX_NAME_LENGTH: usize = 12;
+
+const MIN_UI_SCALE: f32 = 0.5;
+const MAX_UI_SCALE: f32 = 2.0;
+
+impl Settings {
+    /// Volume of sound effects, master volume included.
+    pub fn sfx(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+
+    /// Volume of music, master volume included.
+    pub fn music(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    fn load() -> Self {
+        let mut settings = Settings::default();
+        let Some(stored) = storage::read("settings") else {
+            return settings;
+        };
+        // one `key=value` per line, unknown keys and bad values are skipped
+        for (key, value) in stored.lines().filter_map(|line| line.split_once('=')) {
+            let value = value.trim();
+            match key.trim() {
+                "master_volume" => parse_into(value, &mut settings.master_volume),
+                "sfx_volume" => parse_into(value, &mut settings.sfx_volume),
+                "music_volume" => parse_into(value, &mut settings.music_volume),
+                "fullscreen" => parse_into(value, &mut settings.fullscreen),
+                "vsync" => parse_into(value, &mut settings.vsync),
+                "ui_scale" => parse_into(value, &mut settings.ui_scale),
+                "speedrun_timer" => parse_into(value, &mut settings.speedrun_timer),
+                "player_name" if !value.is_empty() => {
+                    settings.player_name = value.chars().take(MAX_NAME_LENGTH).collect()
+                }
+                "leaderboard_url" => settings.leaderboard_url = value.to_string(),
+                _ => (),
+            }
+        }
+        settings.master_volume = settings.master_volume.clamp(0., 1.);
+        settings.sfx_volume = settings.sfx_volume.clamp(0., 1.);
+        settings.music_volume = settings.music_volume.clamp(0., 1.);
+        settings.ui_scale = settings.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        settings
+    }
+
+    fn save(&self) {
+        let contents = format!(
+            "master_volume={}\nsfx_volume={}\nmusic_volume={}\nfullscreen={}\nvsync={}\nui_scale={}\nspeedrun_timer={}\nplayer_name={}\nleaderboard_url={}\n",
+            self.master_volume,
+            self.sfx_volume,
+            self.music_volume,
+            self.fullscreen,
+            self.vsync,
+            self.ui_scale,
+            self.speedrun_timer,
+            self.player_name,
+            self.leaderboard_url,
+        );
+        storage::write("settings", &contents);
+    }
+}
+
+fn parse_into<T: std::str::FromStr>(value: &str, field: &mut T) {
+    if let Ok(value) = value.parse() {
+        *field = value;
+    }
+}
+
+/// Small text files kept between sessions, in the config directory or the browser's local storage.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod storage {
+    use std::{fs, path::PathBuf};
+
+    use bevy::log::warn;
+
+    fn path(name: &str) -> Option<PathBuf> {
+        let config = std::env::var_os("APPDATA")
+            .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(
+            config
+                .join("worst-physics-engine-ever")
+                .join(format!("{name}.txt")),
+        )
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        fs::read_to_string(path(name)?).ok()
+    }
+
+    pub fn write(name: &str, contents: &str) {
+        let Some(path) = path(name) else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(error) = fs::write(&path, contents) {
+            warn!("Couldn't save {} to {}: {}", name, path.display(), error);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub mod storage {
+    fn key(name: &str) -> String {
+        format!("worst-physics-engine-ever.{name}")
+    }
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(&key(name)).ok()?
+    }
+
+    pub fn write(name: &str, contents: &str) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.set_item(&key(name), contents);
+        }
+    }
+}
+
+fn apply_settings(
+    settings: Res<Settings>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut window in &mut window {
+        let mode = if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        let present_mode = if settings.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+    if ui_scale.0 != settings.ui_scale as f64 {
+        ui_scale.0 = settings.ui_scale as f64;
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq)]
+enum Setting {
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    Fullscreen,
+    Vsync,
+    UiScale,
+    SpeedrunTimer,
+}
+
+impl Setting {
+    fn name(&self) -> &'static str {
+        match self {
+            Setting::MasterVolume => "Master",
+            Setting::SfxVolume => "Sound",
+            Setting::MusicVolume => "Music",
+            Setting::Fullscreen => "Fullscreen",
+            Setting::Vsync => "VSync",
+            Setting::UiScale => "UI scale",
+            Setting::SpeedrunTimer => "Speedrun",
+        }
+    }
+
+    // position of the slider, between 0 and 1
+    fn fraction(&self, settings: &Settings) -> f32 {
+        match self {
+            Setting::MasterVolume => settings.master_volume,
+            Setting::SfxVolume => settings.sfx_volume,
+            Setting::MusicVolume => settings.music_volume,
+            Setting::Fullscreen => settings.fullscreen as u8 as f32,
+            Setting::Vsync => settings.vsync as u8 as f32,
+            Setting::SpeedrunTimer => settings.speedrun_timer as u8 as f32,
+            Setting::UiScale => (settings.ui_scale - MIN_UI_SCALE) / (MAX_UI_SCALE - MIN_UI_SCALE),
+        }
+    }
+
+    fn set_fraction(&self, settings: &mut Settings, fraction: f32) {
+        let fraction = fraction.clamp(0., 1.);
+        match self {
+            // steps of 5%
+            Setting::MasterVolume => settings.master_volume = (fraction * 20.).round() / 20.,
+            Setting::SfxVolume => settings.sfx_volume = (fraction * 20.).round() / 20.,
+            Setting::MusicVolume => settings.music_volume = (fraction * 20.).round() / 20.,
+            Setting::Fullscreen => settings.fullscreen = fraction > 0.5,
+            Setting::Vsync => settings.vsync = fraction > 0.5,
+            Setting::SpeedrunTimer => settings.speedrun_timer = fraction > 0.5,
+            // steps of 0.1
+            Setting::UiScale => {
+                settings.ui_scale =
+                    ((MIN_UI_SCALE + fraction * (MAX_UI_SCALE - MIN_UI_SCALE)) * 10.).round() / 10.
+            }
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> String {
+        match self {
+            Setting::Fullscreen | Setting::Vsync | Setting::SpeedrunTimer => {
+                if self.fraction(settings) > 0.5 {
+                    "On".to_string()
+                } else {
+                    "Off".to_string()
+                }
+            }
+            Setting::UiScale => format!("{:.1}x", settings.ui_scale),
+            _ => format!("{:.0}%", self.fraction(settings) * 100.),
+        }
+    }
+}
+
+#[derive(Component)]
+struct OnSettingsScreen;
+
+// Track of a slider, pressing or dragging along it sets the value.
+#[derive(Component)]
+struct Slider(Setting);
+
+#[derive(Component)]
+struct SliderFill(Setting);
+
+#[derive(Component)]
+struct SettingValue(Setting);
+
+#[derive(Component)]
+struct NameValue;
+
+// Typed characters go to the player name while set.
+#[derive(Resource, Default)]
+struct EditingName(bool);
+
+fn exit_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<OnSettingsScreen>>,
+    mut settings: ResMut<Settings>,
+) {
+    for entity in &mut query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    // the name may have been left empty while typing
+    if settings.player_name.trim().is_empty() {
+        settings.player_name = DEFAULT_PLAYER_NAME.to_string();
+    }
+    settings.save();
+}
+
+fn setup(
+    mut commands: Commands,
+    font: Res<FontHandle>,
+    settings: Res<Settings>,
+    mut editing_name: ResMut<EditingName>,
+) {
+    editing_name.0 = false;
+
+    let button_style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(60.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(5.0)),
+        ..default()
+    };
+    let text_style = TextStyle {
+        font_size: 25.0,
+        color: TEXT_COLOR,
+        font: font.0.clone(),
+    };
+    let row_style = Style {
+        flex_direction: FlexDirection::Row,
+        align_items: AlignItems::Center,
+        margin: UiRect::all(Val::Px(5.0)),
+        ..default()
+    };
+    let label_style = Style {
+        width: Val::Px(250.0),
+        ..default()
+    };
+    let value_style = Style {
+        width: Val::Px(120.0),
+        margin: UiRect::left(Val::Px(20.0)),
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::rgba(0.3, 0.3, 0.3, 0.5).into(),
+                ..default()
+            },
+            OnSettingsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Settings",
+                    TextStyle {
+                        font_size: 60.0,
+                        color: TEXT_COLOR,
+                        font: font.0.clone(),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(40.0)),
+                    ..default()
+                }),
+            );
+
+            for setting in [
+                Setting::MasterVolume,
+                Setting::SfxVolume,
+                Setting::MusicVolume,
+                Setting::UiScale,
+            ] {
+                parent
+                    .spawn(NodeBundle {
+                        style: row_style.clone(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(setting.name(), text_style.clone())
+                                .with_style(label_style.clone()),
+                        );
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(300.0),
+                                        height: Val::Px(30.0),
+                                        border: UiRect::all(Val::Px(5.0)),
+                                        ..default()
+                                    },
+                                    background_color: NORMAL_BUTTON.into(),
+                                    border_color: BorderColor(HOVERED_BUTTON),
+                                    ..default()
+                                },
+                                Slider(setting),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            width: Val::Percent(setting.fraction(&settings) * 100.),
+                                            height: Val::Percent(100.0),
+                                            ..default()
+                                        },
+                                        background_color: PRESSED_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    SliderFill(setting),
+                                ));
+                            });
+                        parent.spawn((
+                            TextBundle::from_section(setting.value(&settings), text_style.clone())
+                                .with_style(value_style.clone()),
+                            SettingValue(setting),
+                        ));
+                    });
+            }
+
+            for setting in [Setting::Fullscreen, Setting::Vsync, Setting::SpeedrunTimer] {
+                parent
+                    .spawn(NodeBundle {
+                        style: row_style.clone(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(setting.name(), text_style.clone())
+                                .with_style(label_style.clone()),
+                        );
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(120.0),
+                                        ..button_style.clone()
+                                    },
+                                    background_color: NORMAL_BUTTON.into(),
+                                    border_color: BorderColor(HOVERED_BUTTON),
+                                    ..default()
+                                },
+                                ButtonAction::Toggle(setting),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    TextBundle::from_section(
+                                        setting.value(&settings),
+                                        text_style.clone(),
+                                    ),
+                                    SettingValue(setting),
+                                ));
+                            });
+                    });
+            }
+
+            parent
+                .spawn(NodeBundle {
+                    style: row_style.clone(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section("Name", text_style.clone())
+                            .with_style(label_style.clone()),
+                    );
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(300.0),
+                                    ..button_style.clone()
+                                },
+                                background_color: NORMAL_BUTTON.into(),
+                                border_color: BorderColor(HOVERED_BUTTON),
+                                ..default()
+                            },
+                            ButtonAction::EditName,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    settings.player_name.clone(),
+                                    text_style.clone(),
+                                ),
+                                NameValue,
+                            ));
+                        });
+                });
+
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            margin: UiRect::all(Val::Px(40.0)),
+                            ..button_style.clone()
+                        },
+                        background_color: NORMAL_BUTTON.into(),
+                        border_color: BorderColor(HOVERED_BUTTON),
+                        ..default()
+                    },
+                    ButtonAction::Back,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Back", text_style.clone()));
+                });
+        });
+}
+
+#[derive(Component)]
+enum ButtonAction {
+    Toggle(Setting),
+    EditName,
+    Back,
+}
+
+#[allow(clippy::type_complexity)]
+fn button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &ButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut next_state: ResMut<NextState<GameMode>>,
+    mut settings: ResMut<Settings>,
+    mut editing_name: ResMut<EditingName>,
+    mut audio_events: EventWriter<AudioEvent>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        *color = match *interaction {
+            Interaction::Pressed => {
+                audio_events.send(AudioEvent::new("click"));
+                match button {
+                    ButtonAction::Toggle(setting) => {
+                        let fraction = setting.fraction(&settings);
+                        setting.set_fraction(&mut settings, 1. - fraction);
+                    }
+                    ButtonAction::EditName => editing_name.0 = !editing_name.0,
+                    ButtonAction::Back => next_state.set(GameMode::Menu),
+                }
+                PRESSED_BUTTON.into()
+            }
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+fn drag_sliders(
+    sliders: Query<(&Interaction, &Node, &GlobalTransform, &Slider)>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    touches: Res<Touches>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(pointer) = window
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .or_else(|| touches.first_pressed_position())
+    else {
+        return;
+    };
+    for (interaction, node, transform, slider) in &sliders {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let rect = node.logical_rect(transform);
+        let fraction = (pointer.x - rect.min.x) / rect.width();
+        let mut changed = (*settings).clone();
+        slider.0.set_fraction(&mut changed, fraction);
+        // only touch the resource on actual changes, the window and audio react to them
+        if changed != *settings {
+            *settings = changed;
+        }
+    }
+}
+
+fn type_name(
+    mut editing_name: ResMut<EditingName>,
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut settings: ResMut<Settings>,
+) {
+    if !editing_name.0 {
+        characters.clear();
+        return;
+    }
+    let mut name = settings.player_name.clone();
+    for event in characters.read() {
+        if !event.char.is_control() && name.chars().count() < MAX_NAME_LENGTH {
+            name.push(event.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+    if keys.any_just_pressed([KeyCode::Return, KeyCode::Escape]) {
+        editing_name.0 = false;
+        name = name.trim().to_string();
+        if name.is_empty() {
+            name = DEFAULT_PLAYER_NAME.to_string();
+        }
+    }
+    if name != settings.player_name {
+        settings.player_name = name;
+    }
+}
+
+fn update_name(
+    settings: Res<Settings>,
+    editing_name: Res<EditingName>,
+    mut values: Query<&mut Text, With<NameValue>>,
+) {
+    if !settings.is_changed() && !editing_name.is_changed() {
+        return;
+    }
+    for mut text in &mut values {
+        text.sections[0].value = if editing_name.0 {
+            format!("{}_", settings.player_name)
+        } else {
+            settings.player_name.clone()
+        };
+    }
+}
+
+fn update_widgets(
+    settings: Res<Settings>,
+    mut fills: Query<(&mut Style, &SliderFill)>,
+    mut values: Query<(&mut Text, &SettingValue)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut style, fill) in &mut fills {
+        style.width = Val::Percent(fill.0.fraction(&settings) * 100.);
+    }
+    for (mut text, value) in &mut values {
+        text.sections[0].value = value.0.value(&settings);
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+
+use crate::{
+    settings::Settings, CurrentLevel, FontHandle, GameKind, GameMode, PauseState, LEVELS,
+    TEXT_COLOR,
+};
+
+pub struct SpeedrunPlugin;
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Speedrun>()
+            .add_systems(OnEnter(GameMode::Play), (start_run, setup_timer).chain())
+            .add_systems(OnExit(GameMode::Play), exit_mode)
+            .add_systems(OnEnter(GameMode::Won), split)
+            .add_systems(OnEnter(GameMode::Menu), abandon)
+            .add_systems(
+                Update,
+                (tick, update_timer)
+                    .chain()
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+/// A run through every level of the platformer, timed from entering the first one to winning
+/// the last one. Retries after a loss and time spent editing colliders count towards the time,
+/// only pausing stops the clock.
+#[derive(Resource, Default)]
+pub struct Speedrun {
+    pub running: bool,
+    pub elapsed: f32,
+    /// Time at which each level was won during the current run.
+    pub splits: Vec<f32>,
+    /// Splits of the fastest finished run.
+    pub best_splits: Option<Vec<f32>>,
+}
+
+impl Speedrun {
+    // how far ahead (negative) or behind the best run the last split was
+    fn last_delta(&self) -> Option<f32> {
+        let split = self.splits.last()?;
+        let best = self.best_splits.as_ref()?.get(self.splits.len() - 1)?;
+        Some(split - best)
+    }
+}
+
+#[derive(Component)]
+struct OnSpeedrunTimer;
+
+#[derive(Component)]
+struct SpeedrunText;
+
+fn format_time(seconds: f32) -> String {
+    format!("{}:{:05.2}", (seconds / 60.).floor(), seconds % 60.)
+}
+
+fn start_run(
+    mut speedrun: ResMut<Speedrun>,
+    settings: Res<Settings>,
+    game_kind: Res<State<GameKind>>,
+    level: Res<CurrentLevel>,
+) {
+    if !settings.speedrun_timer || *game_kind.get() != GameKind::Platformer {
+        speedrun.running = false;
+        return;
+    }
+    if level.0 == 0 && !speedrun.running {
+        speedrun.running = true;
+        speedrun.elapsed = 0.;
+        speedrun.splits.clear();
+    } else if speedrun.running && level.0 != speedrun.splits.len() {
+        // levels were skipped or replayed, the run no longer counts
+        speedrun.running = false;
+    }
+}
+
+fn abandon(mut speedrun: ResMut<Speedrun>) {
+    speedrun.running = false;
+}
+
+fn tick(mut speedrun: ResMut<Speedrun>, game_mode: Res<State<GameMode>>, time: Res<Time>) {
+    // the clock keeps going on the win and lose screens and while editing between attempts
+    if speedrun.running
+        && matches!(
+            game_mode.get(),
+            GameMode::Play | GameMode::Edit | GameMode::Won | GameMode::Lost
+        )
+    {
+        speedrun.elapsed += time.delta_seconds();
+    }
+}
+
+fn split(mut speedrun: ResMut<Speedrun>, level: Res<CurrentLevel>) {
+    if !speedrun.running || level.0 != speedrun.splits.len() {
+        return;
+    }
+    let elapsed = speedrun.elapsed;
+    speedrun.splits.push(elapsed);
+    if speedrun.splits.len() == LEVELS.len() {
+        speedrun.running = false;
+        let best = speedrun
+            .best_splits
+            .as_ref()
+            .and_then(|best| best.last().copied());
+        if best.map_or(true, |best| elapsed < best) {
+            speedrun.best_splits = Some(speedrun.splits.clone());
+        }
+    }
+}
+
+fn exit_mode(mut commands: Commands, query: Query<Entity, With<OnSpeedrunTimer>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn setup_timer(mut commands: Commands, speedrun: Res<Speedrun>, font: Res<FontHandle>) {
+    if !speedrun.running {
+        return;
+    }
+    let style = TextStyle {
+        font_size: 25.0,
+        color: TEXT_COLOR,
+        font: font.0.clone(),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(12.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnSpeedrunTimer,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_sections([
+                    TextSection::new("", style.clone()),
+                    TextSection::new("", style),
+                ]),
+                SpeedrunText,
+            ));
+        });
+}
+
+fn update_timer(speedrun: Res<Speedrun>, mut text: Query<&mut Text, With<SpeedrunText>>) {
+    for mut text in &mut text {
+        text.sections[0].value = format!(
+            "{} {}/{}",
+            format_time(speedrun.elapsed),
+            speedrun.splits.len() + 1,
+            LEVELS.len()
+        );
+        match speedrun.last_delta() {
+            Some(delta) => {
+                text.sections[1].value = format!(" {:+.2}", delta);
+                text.sections[1].style.color = if delta <= 0. {
+                    Color::GREEN
+                } else {
+                    Color::RED
+                };
+            }
+            None => text.sections[1].value.clear(),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{FontHandle, GameMode, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR};
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchControls>()
+            .add_systems(
+                PreUpdate,
+                (
+                    detect_touch,
+                    update_controls.run_if(in_state(GameMode::Play)),
+                )
+                    .chain()
+                    .after(InputSystem),
+            )
+            .add_systems(
+                Update,
+                (show_controls, move_knob).run_if(in_state(GameMode::Play)),
+            )
+            .add_systems(OnEnter(GameMode::Play), setup_controls)
+            .add_systems(OnExit(GameMode::Play), exit_mode);
+    }
+}
+
+/// State of the on-screen controls, read alongside the keyboard.
+#[derive(Resource, Default)]
+pub struct TouchControls {
+    /// Whether the screen has been touched, the on-screen controls are shown from then on.
+    pub enabled: bool,
+    /// Direction of the virtual joystick, inside the unit circle.
+    pub stick: Vec2,
+    pub jump_pressed: bool,
+    pub jump_just_pressed: bool,
+    // each control follows the first finger that touched it, other fingers are ignored
+    stick_touch: Option<u64>,
+    jump_touch: Option<u64>,
+}
+
+impl TouchControls {
+    pub fn left(&self) -> bool {
+        self.stick.x < -0.5
+    }
+
+    pub fn right(&self) -> bool {
+        self.stick.x > 0.5
+    }
+
+    pub fn up(&self) -> bool {
+        self.stick.y > 0.5
+    }
+
+    pub fn down(&self) -> bool {
+        self.stick.y < -0.5
+    }
+
+    fn release(&mut self) {
+        self.stick = Vec2::ZERO;
+        self.jump_pressed = false;
+        self.jump_just_pressed = false;
+        self.stick_touch = None;
+        self.jump_touch = None;
+    }
+}
+
+#[derive(Component)]
+struct OnTouchControls;
+
+#[derive(Component)]
+struct Joystick;
+
+#[derive(Component)]
+struct Knob;
+
+#[derive(Component)]
+struct JumpButton;
+
+const STICK_SIZE: f32 = 140.0;
+const KNOB_SIZE: f32 = 60.0;
+
+fn exit_mode(
+    mut commands: Commands,
+    query: Query<Entity, With<OnTouchControls>>,
+    mut controls: ResMut<TouchControls>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    controls.release();
+}
+
+fn setup_controls(mut commands: Commands, controls: Res<TouchControls>, font: Res<FontHandle>) {
+    let visibility = if controls.enabled {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(24.0),
+                    bottom: Val::Px(24.0),
+                    width: Val::Px(STICK_SIZE),
+                    height: Val::Px(STICK_SIZE),
+                    ..default()
+                },
+                background_color: Color::rgba(0.15, 0.15, 0.15, 0.5).into(),
+                visibility,
+                ..default()
+            },
+            Joystick,
+            OnTouchControls,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px((STICK_SIZE - KNOB_SIZE) / 2.0),
+                        top: Val::Px((STICK_SIZE - KNOB_SIZE) / 2.0),
+                        width: Val::Px(KNOB_SIZE),
+                        height: Val::Px(KNOB_SIZE),
+                        ..default()
+                    },
+                    background_color: TEXT_COLOR.with_a(0.7).into(),
+                    ..default()
+                },
+                Knob,
+            ));
+        });
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(24.0),
+                    bottom: Val::Px(24.0),
+                    width: Val::Px(110.0),
+                    height: Val::Px(110.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: NORMAL_BUTTON.with_a(0.7).into(),
+                visibility,
+                ..default()
+            },
+            JumpButton,
+            OnTouchControls,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Jump",
+                TextStyle {
+                    font_size: 20.0,
+                    color: TEXT_COLOR,
+                    font: font.0.clone(),
+                },
+            ));
+        });
+}
+
+fn detect_touch(touches: Res<Touches>, mut controls: ResMut<TouchControls>) {
+    if !controls.enabled && touches.any_just_pressed() {
+        controls.enabled = true;
+    }
+}
+
+// Touches are handled here rather than through `Interaction`, which only follows one finger.
+fn update_controls(
+    touches: Res<Touches>,
+    mut controls: ResMut<TouchControls>,
+    joystick: Query<(&Node, &GlobalTransform), With<Joystick>>,
+    jump_button: Query<(&Node, &GlobalTransform), With<JumpButton>>,
+) {
+    let (Ok(joystick), Ok(jump_button)) = (joystick.get_single(), jump_button.get_single()) else {
+        controls.release();
+        return;
+    };
+    let joystick = joystick.0.logical_rect(joystick.1);
+    let jump_button = jump_button.0.logical_rect(jump_button.1);
+
+    controls.jump_just_pressed = false;
+    for touch in touches.iter_just_pressed() {
+        if controls.stick_touch.is_none() && joystick.contains(touch.position()) {
+            controls.stick_touch = Some(touch.id());
+        } else if controls.jump_touch.is_none() && jump_button.contains(touch.position()) {
+            controls.jump_touch = Some(touch.id());
+            controls.jump_just_pressed = true;
+        }
+    }
+
+    match controls.stick_touch.and_then(|id| touches.get_pressed(id)) {
+        Some(touch) => {
+            // screen y goes down
+            let offset = (touch.position() - joystick.center()) / joystick.half_size();
+            controls.stick = (offset * Vec2::new(1., -1.)).clamp_length_max(1.);
+        }
+        None => {
+            controls.stick_touch = None;
+            controls.stick = Vec2::ZERO;
+        }
+    }
+
+    controls.jump_pressed = controls
+        .jump_touch
+        .and_then(|id| touches.get_pressed(id))
+        .is_some();
+    if !controls.jump_pressed {
+        controls.jump_touch = None;
+    }
+}
+
+fn show_controls(
+    controls: Res<TouchControls>,
+    mut visibility: Query<&mut Visibility, With<OnTouchControls>>,
+) {
+    if controls.is_changed() && controls.enabled {
+        for mut visibility in &mut visibility {
+            if *visibility == Visibility::Hidden {
+                *visibility = Visibility::Inherited;
+            }
+        }
+    }
+}
+
+fn move_knob(
+    controls: Res<TouchControls>,
+    mut knob: Query<&mut Style, With<Knob>>,
+    mut jump_button: Query<&mut BackgroundColor, With<JumpButton>>,
+) {
+    let travel = (STICK_SIZE - KNOB_SIZE) / 2.0;
+    for mut style in &mut knob {
+        style.left = Val::Px(travel + controls.stick.x * travel);
+        style.top = Val::Px(travel - controls.stick.y * travel);
+    }
+    for mut color in &mut jump_button {
+        *color = if controls.jump_pressed {
+            PRESSED_BUTTON.with_a(0.7).into()
+        } else {
+            NORMAL_BUTTON.with_a(0.7).into()
+        };
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+
+use crate::audio::AudioEvent;
+
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (fade_in, slide_in, reveal));
+    }
+}
+
+/// Fades the background of a node in to the given alpha.
+#[derive(Component)]
+pub struct FadeIn {
+    pub timer: Timer,
+    pub alpha: f32,
+}
+
+impl FadeIn {
+    pub fn new(seconds: f32, alpha: f32) -> Self {
+        FadeIn {
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+            alpha,
+        }
+    }
+}
+
+/// Slides a node down from `from` pixels above its place in the layout.
+#[derive(Component)]
+pub struct SlideIn {
+    pub timer: Timer,
+    pub from: f32,
+}
+
+impl SlideIn {
+    pub fn new(seconds: f32, from: f32) -> Self {
+        SlideIn {
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+            from,
+        }
+    }
+}
+
+/// Keeps a node hidden for a while, then shows it with a sound.
+#[derive(Component)]
+pub struct Reveal {
+    pub delay: Timer,
+    pub sound: Option<AudioEvent>,
+}
+
+impl Reveal {
+    pub fn after(seconds: f32) -> Self {
+        Reveal {
+            delay: Timer::from_seconds(seconds, TimerMode::Once),
+            sound: None,
+        }
+    }
+
+    pub fn with_sound(mut self, sound: AudioEvent) -> Self {
+        self.sound = Some(sound);
+        self
+    }
+}
+
+fn ease_out(t: f32) -> f32 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+fn fade_in(
+    mut commands: Commands,
+    mut nodes: Query<(Entity, &mut FadeIn, &mut BackgroundColor)>,
+    time: Res<Time>,
+) {
+    for (entity, mut fade, mut color) in &mut nodes {
+        fade.timer.tick(time.delta());
+        color.0.set_a(fade.alpha * fade.timer.percent());
+        if fade.timer.finished() {
+            commands.entity(entity).remove::<FadeIn>();
+        }
+    }
+}
+
+fn slide_in(
+    mut commands: Commands,
+    mut nodes: Query<(Entity, &mut SlideIn, &mut Style)>,
+    time: Res<Time>,
+) {
+    for (entity, mut slide, mut style) in &mut nodes {
+        slide.timer.tick(time.delta());
+        style.top = Val::Px(-slide.from * (1.0 - ease_out(slide.timer.percent())));
+        if slide.timer.finished() {
+            commands.entity(entity).remove::<SlideIn>();
+        }
+    }
+}
+
+fn reveal(
+    mut commands: Commands,
+    mut nodes: Query<(Entity, &mut Reveal, &mut Visibility)>,
+    mut audio_events: EventWriter<AudioEvent>,
+    time: Res<Time>,
+) {
+    for (entity, mut reveal, mut visibility) in &mut nodes {
+        if !reveal.delay.tick(time.delta()).finished() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+        if let Some(sound) = reveal.sound.take() {
+            audio_events.send(sound);
+        }
+        commands.entity(entity).remove::<Reveal>();
+    }
+}
@@ -1,11 +1,22 @@
 use bevy::prelude::*;
 use bevy_ecs_ldtk::assets::LdtkProject;
+use bevy_rapier2d::prelude::RigidBody;
 
 use crate::{
-    audio::AudioEvent, edit::EnabledColliders, CurrentLevel, FontHandle, GameMode, Progression,
+    audio::AudioEvent,
+    edit::EnabledColliders,
+    leaderboard::{self, LeaderboardEntry, Leaderboards, MAX_ENTRIES},
+    menu::load_level,
+    play::Playthrough,
+    settings::Settings,
+    transition::{FadeIn, Reveal, SlideIn},
+    CurrentLevel, FontHandle, GameKind, GameMode, LdtkHandle, LevelRecord, Progression,
     HOVERED_BUTTON, LEVELS, NORMAL_BUTTON, PRESSED_BUTTON, TEXT_COLOR,
 };
 
+#[cfg(feature = "online-leaderboard")]
+use crate::{leaderboard_client::LeaderboardClient, replay::Replay};
+
 pub struct WonPlugin;
 
 impl Plugin for WonPlugin {
@@ -19,25 +30,75 @@ impl Plugin for WonPlugin {
 #[derive(Component)]
 struct OnWonScreen;
 
+const SLIDE_DISTANCE: f32 = 600.0;
+
 fn exit_screen(mut commands: Commands, query: Query<Entity, With<OnWonScreen>>) {
     for entity in &mut query.iter() {
         commands.entity(entity).despawn_recursive();
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn setup(
     mut commands: Commands,
     colliders: Res<EnabledColliders>,
     font: Res<FontHandle>,
     mut progression: ResMut<Progression>,
     level: Res<CurrentLevel>,
+    playthrough: Res<Playthrough>,
+    rigid_bodies: Query<&RigidBody>,
     asset_server: Res<AssetServer>,
+    mut leaderboards: ResMut<Leaderboards>,
+    settings: Res<Settings>,
+    game_kind: Res<State<GameKind>>,
+    #[cfg(feature = "online-leaderboard")] client: Option<Res<LeaderboardClient>>,
+    #[cfg(feature = "online-leaderboard")] replay: Res<Replay>,
 ) {
-    progression.levels[level.0] = LEVELS[level.0]
+    let info = &LEVELS[level.0];
+    let rank = info
         .thresholds
-        .binary_search(&colliders.coords.len())
-        .map_or_else(|err| err, |ok| ok)
-        .min(progression.levels[level.0]);
+        .binary_search(&colliders.count())
+        .unwrap_or_else(|rank| rank);
+    progression.levels[level.0] = rank.min(progression.levels[level.0]);
+
+    let record = LevelRecord {
+        colliders: colliders.count(),
+        time: playthrough.timer.elapsed_secs(),
+    };
+    let previous_best = progression.records[level.0];
+    if previous_best.map_or(true, |best| record.beats(&best)) {
+        progression.records[level.0] = Some(record);
+    }
+    let time_rank = info.time_rank(record.time);
+    progression.time_trials[level.0] = time_rank.min(progression.time_trials[level.0]);
+    let previous_best_time = progression.best_times[level.0];
+    progression.best_times[level.0] =
+        Some(previous_best_time.map_or(record.time, |best| best.min(record.time)));
+    // the fastest time, once there is an earlier win to compare against
+    let best_time = previous_best_time.and(progression.best_times[level.0]);
+
+    let rigid_bodies = rigid_bodies
+        .iter()
+        .filter(|body| matches!(body, RigidBody::Dynamic))
+        .count();
+    let entry = LeaderboardEntry {
+        name: settings.player_name.clone(),
+        colliders: record.colliders,
+        rigid_bodies,
+        time: record.time,
+        date: leaderboard::now(),
+        solution: colliders.solution_code(),
+    };
+    // in the platformer the colliders are the level's defaults, not a solution worth ranking
+    let ranked = matches!(game_kind.get(), GameKind::Puzzle);
+    #[cfg(feature = "online-leaderboard")]
+    if let Some(client) = client.filter(|_| ranked) {
+        client.submit(level.0, entry.clone(), &replay);
+    }
+    let place = ranked.then(|| leaderboards.submit(level.0, entry));
+    let next_level = level.0 + 1;
+    let next_unlocked =
+        next_level < LEVELS.len() && progression.levels[next_level - 1] != usize::MAX;
 
     // Common style for all buttons on the screen
     let button_style = Style {
@@ -54,6 +115,47 @@ fn setup(
         color: TEXT_COLOR,
         font: font.0.clone(),
     };
+    let stat_style = TextStyle {
+        font_size: 25.0,
+        color: TEXT_COLOR,
+        font: font.0.clone(),
+    };
+
+    let best_text = match previous_best {
+        None => "First clear!".to_string(),
+        Some(best) if record.beats(&best) => format!(
+            "New personal best! (was {} colliders, {:.1}s)",
+            best.colliders, best.time
+        ),
+        Some(best) => format!(
+            "Personal best: {} colliders, {:.1}s",
+            best.colliders, best.time
+        ),
+    };
+    let next_star_text = match rank {
+        0 => "All stars earned!".to_string(),
+        rank => format!(
+            "{} colliders or fewer for the {} star",
+            info.thresholds[rank - 1],
+            ["gold", "silver", "bronze"][rank - 1]
+        ),
+    };
+    let time_trial_text = match (time_rank, best_time) {
+        (0, _) => "Time trial: all stars earned!".to_string(),
+        (rank, Some(best)) => format!(
+            "Time trial: {:.1}s for {}, best {:.1}s",
+            info.time_thresholds[rank - 1],
+            ["gold", "silver", "bronze"][rank - 1],
+            best
+        ),
+        (rank, None) => format!(
+            "Time trial: {:.1}s for {}",
+            info.time_thresholds[rank - 1],
+            ["gold", "silver", "bronze"][rank - 1]
+        ),
+    };
+    // the time trial stars come after the collider ones
+    let time_stars_delay = 0.8 + 0.4 * (3 - rank.min(3)) as f32;
 
     commands
         .spawn((
@@ -66,173 +168,234 @@ fn setup(
                     flex_direction: FlexDirection::Column,
                     ..default()
                 },
-                background_color: Color::rgba(0.3, 0.3, 0.3, 0.5).into(),
+                background_color: Color::rgba(0.3, 0.3, 0.3, 0.0).into(),
                 ..default()
             },
+            FadeIn::new(0.4, 0.5),
             OnWonScreen,
         ))
         .with_children(|parent| {
-            parent.spawn(
-                TextBundle::from_section(
-                    "You won!",
-                    TextStyle {
-                        font_size: 80.0,
-                        color: TEXT_COLOR,
-                        font: font.0.clone(),
-                    },
-                )
-                .with_style(Style {
-                    margin: UiRect::all(Val::Px(50.0)),
-                    ..default()
-                }),
-            );
-            parent.spawn(
-                TextBundle::from_section(
-                    format!("with {} colliders", colliders.coords.len()),
-                    TextStyle {
-                        font_size: 40.0,
-                        color: TEXT_COLOR,
-                        font: font.0.clone(),
-                    },
-                )
-                .with_style(Style {
-                    margin: UiRect::all(Val::Px(50.0)),
-                    ..default()
-                }),
-            );
-
-            let percent = (colliders.coords.len() as f32 - LEVELS[level.0].max_colliders as f32)
-                .abs()
-                / (LEVELS[level.0].max_colliders as f32 - LEVELS[level.0].thresholds[0] as f32);
-            let gold =
-                (LEVELS[level.0].thresholds[0] as f32 - LEVELS[level.0].max_colliders as f32).abs()
-                    / (LEVELS[level.0].max_colliders as f32 - LEVELS[level.0].thresholds[0] as f32);
-            let silver =
-                (LEVELS[level.0].thresholds[1] as f32 - LEVELS[level.0].max_colliders as f32).abs()
-                    / (LEVELS[level.0].max_colliders as f32 - LEVELS[level.0].thresholds[0] as f32);
-            let bronze =
-                (LEVELS[level.0].thresholds[2] as f32 - LEVELS[level.0].max_colliders as f32).abs()
-                    / (LEVELS[level.0].max_colliders as f32 - LEVELS[level.0].thresholds[0] as f32);
             parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        flex_direction: FlexDirection::Row,
-                        width: Val::Px(500.0),
-                        height: Val::Px(50.0),
-                        ..default()
-                    },
-                    ..default()
-                })
-                .with_children(|parent| {
-                    parent.spawn(NodeBundle {
-                        style: Style {
-                            width: Val::Percent(percent * 100.0),
-                            height: Val::Px(30.0),
-                            ..default()
-                        },
-                        background_color: Color::rgb(0.0, 1.0, 0.0).into(),
-                        ..default()
-                    });
-                    parent.spawn(NodeBundle {
+                .spawn((
+                    NodeBundle {
                         style: Style {
-                            width: Val::Percent((1.0 - percent) * 100.0),
-                            height: Val::Px(30.0),
+                            top: Val::Px(-SLIDE_DISTANCE),
+                            align_items: AlignItems::Center,
+                            flex_direction: FlexDirection::Column,
                             ..default()
                         },
-                        background_color: Color::rgb(0.0, 0.0, 0.0).into(),
                         ..default()
-                    });
-                    parent.spawn(ImageBundle {
-                        style: Style {
-                            position_type: PositionType::Absolute,
-                            left: Val::Percent(bronze * 100.0 - 2.5),
-                            top: Val::Px(-15.0),
-                            width: Val::Px(25.0),
-                            height: Val::Px(25.0),
-                            ..default()
-                        },
-                        image: UiImage::new(asset_server.load("starBronze.png")),
-                        background_color: BackgroundColor(if progression.levels[level.0] < 3 {
-                            Color::WHITE
-                        } else {
-                            Color::GRAY
-                        }),
-                        ..default()
-                    });
-                    parent.spawn(ImageBundle {
-                        style: Style {
-                            position_type: PositionType::Absolute,
-                            left: Val::Percent(silver * 100.0 - 2.5),
-                            top: Val::Px(-15.0),
-                            width: Val::Px(25.0),
-                            height: Val::Px(25.0),
+                    },
+                    SlideIn::new(0.5, SLIDE_DISTANCE),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            "You won!",
+                            TextStyle {
+                                font_size: 80.0,
+                                color: TEXT_COLOR,
+                                font: font.0.clone(),
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(30.0)),
                             ..default()
-                        },
-                        image: UiImage::new(asset_server.load("starSilver.png")),
-                        background_color: BackgroundColor(if progression.levels[level.0] < 2 {
-                            Color::WHITE
-                        } else {
-                            Color::GRAY
                         }),
-                        ..default()
-                    });
-                    parent.spawn(ImageBundle {
-                        style: Style {
-                            position_type: PositionType::Absolute,
-                            left: Val::Percent(gold * 100.0 - 2.5),
-                            top: Val::Px(-15.0),
-                            width: Val::Px(25.0),
-                            height: Val::Px(25.0),
+                    );
+                    let mut stats = vec![
+                        format!("Time: {:.1}s", record.time),
+                        format!("Colliders: {}", record.colliders),
+                        format!("Rigid bodies: {}", rigid_bodies),
+                        best_text,
+                    ];
+                    if let Some(place) = place {
+                        stats.push(match place {
+                            Some(place) => format!("Leaderboard: #{}", place + 1),
+                            None => format!("Not in the top {}", MAX_ENTRIES),
+                        });
+                    }
+                    for stat in stats {
+                        parent.spawn(
+                            TextBundle::from_section(stat, stat_style.clone()).with_style(Style {
+                                margin: UiRect::all(Val::Px(8.0)),
+                                ..default()
+                            }),
+                        );
+                    }
+
+                    let percent = (colliders.count() as f32 - info.max_colliders as f32).abs()
+                        / (info.max_colliders as f32 - info.thresholds[0] as f32);
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                width: Val::Px(500.0),
+                                height: Val::Px(50.0),
+                                margin: UiRect::top(Val::Px(40.0)),
+                                ..default()
+                            },
                             ..default()
-                        },
-                        image: UiImage::new(asset_server.load("starGold.png")),
-                        background_color: BackgroundColor(if progression.levels[level.0] < 1 {
-                            Color::WHITE
-                        } else {
-                            Color::GRAY
-                        }),
-                        ..default()
-                    });
-                });
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(percent * 100.0),
+                                    height: Val::Px(30.0),
+                                    ..default()
+                                },
+                                background_color: Color::rgb(0.0, 1.0, 0.0).into(),
+                                ..default()
+                            });
+                            parent.spawn(NodeBundle {
+                                style: Style {
+                                    width: Val::Percent((1.0 - percent) * 100.0),
+                                    height: Val::Px(30.0),
+                                    ..default()
+                                },
+                                background_color: Color::rgb(0.0, 0.0, 0.0).into(),
+                                ..default()
+                            });
+
+                            // earned stars pop in one by one once the panel is in place
+                            let mut delay = 0.6;
+                            for (star, image) in [
+                                (2, "starBronze.png"),
+                                (1, "starSilver.png"),
+                                (0, "starGold.png"),
+                            ] {
+                                let position = (info.thresholds[star] as f32
+                                    - info.max_colliders as f32)
+                                    .abs()
+                                    / (info.max_colliders as f32 - info.thresholds[0] as f32);
+                                let earned = rank <= star;
+                                let mut node = parent.spawn(ImageBundle {
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        left: Val::Percent(position * 100.0 - 2.5),
+                                        top: Val::Px(-15.0),
+                                        width: Val::Px(25.0),
+                                        height: Val::Px(25.0),
+                                        ..default()
+                                    },
+                                    image: UiImage::new(asset_server.load(image)),
+                                    background_color: BackgroundColor(if earned {
+                                        Color::WHITE
+                                    } else {
+                                        Color::GRAY
+                                    }),
+                                    visibility: if earned {
+                                        Visibility::Hidden
+                                    } else {
+                                        Visibility::Inherited
+                                    },
+                                    ..default()
+                                });
+                                if earned {
+                                    node.insert(
+                                        Reveal::after(delay).with_sound(AudioEvent::new("star")),
+                                    );
+                                    delay += 0.4;
+                                }
+                            }
+                        });
+
+                    parent.spawn(
+                        TextBundle::from_section(next_star_text, stat_style.clone()).with_style(
+                            Style {
+                                margin: UiRect::all(Val::Px(8.0)),
+                                ..default()
+                            },
+                        ),
+                    );
 
-            parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        flex_direction: FlexDirection::Row,
-                        ..default()
-                    },
-                    ..default()
-                })
-                .with_children(|parent| {
                     parent
-                        .spawn((
-                            ButtonBundle {
-                                style: button_style.clone(),
-                                background_color: NORMAL_BUTTON.into(),
-                                border_color: BorderColor(HOVERED_BUTTON),
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                align_items: AlignItems::Center,
+                                margin: UiRect::top(Val::Px(20.0)),
                                 ..default()
                             },
-                            ButtonAction::Menu,
-                        ))
+                            ..default()
+                        })
                         .with_children(|parent| {
-                            parent
-                                .spawn(TextBundle::from_section("Menu", button_text_style.clone()));
+                            let mut delay = time_stars_delay;
+                            for (star, image) in [
+                                (2, "starBronze.png"),
+                                (1, "starSilver.png"),
+                                (0, "starGold.png"),
+                            ] {
+                                let earned = time_rank <= star;
+                                let mut node = parent.spawn(ImageBundle {
+                                    style: Style {
+                                        width: Val::Px(25.0),
+                                        height: Val::Px(25.0),
+                                        margin: UiRect::horizontal(Val::Px(4.0)),
+                                        ..default()
+                                    },
+                                    image: UiImage::new(asset_server.load(image)),
+                                    background_color: BackgroundColor(if earned {
+                                        Color::WHITE
+                                    } else {
+                                        Color::GRAY
+                                    }),
+                                    visibility: if earned {
+                                        Visibility::Hidden
+                                    } else {
+                                        Visibility::Inherited
+                                    },
+                                    ..default()
+                                });
+                                if earned {
+                                    node.insert(
+                                        Reveal::after(delay).with_sound(AudioEvent::new("star")),
+                                    );
+                                    delay += 0.4;
+                                }
+                            }
                         });
+                    parent.spawn(
+                        TextBundle::from_section(time_trial_text, stat_style.clone()).with_style(
+                            Style {
+                                margin: UiRect::all(Val::Px(8.0)),
+                                ..default()
+                            },
+                        ),
+                    );
+
+                    let mut actions =
+                        vec![(ButtonAction::Menu, "Menu"), (ButtonAction::Retry, "Retry")];
+                    if next_unlocked {
+                        actions.push((ButtonAction::Next(next_level), "Next level"));
+                    }
                     parent
-                        .spawn((
-                            ButtonBundle {
-                                style: button_style.clone(),
-                                background_color: NORMAL_BUTTON.into(),
-                                border_color: BorderColor(HOVERED_BUTTON),
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
                                 ..default()
                             },
-                            ButtonAction::Retry,
-                        ))
+                            ..default()
+                        })
                         .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "Retry",
-                                button_text_style.clone(),
-                            ));
+                            for (action, label) in actions {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: button_style.clone(),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            border_color: BorderColor(HOVERED_BUTTON),
+                                            ..default()
+                                        },
+                                        action,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            label,
+                                            button_text_style.clone(),
+                                        ));
+                                    });
+                            }
                         });
                 });
         });
@@ -242,9 +405,10 @@ fn setup(
 enum ButtonAction {
     Menu,
     Retry,
+    Next(usize),
 }
 
-#[allow(clippy::type_complexity)]
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn button_system(
     mut commands: Commands,
     mut interaction_query: Query<
@@ -253,14 +417,29 @@ fn button_system(
     >,
     mut next_state: ResMut<NextState<GameMode>>,
     world_query: Query<Entity, With<Handle<LdtkProject>>>,
+    world: Res<LdtkHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    game_kind: Res<State<GameKind>>,
     mut audio_events: EventWriter<AudioEvent>,
 ) {
     for (interaction, mut color, button) in &mut interaction_query {
         *color = match *interaction {
             Interaction::Pressed => {
-                audio_events.send(AudioEvent::Click);
+                audio_events.send(AudioEvent::new("click"));
                 match button {
                     ButtonAction::Retry => next_state.set(GameMode::Edit),
+                    ButtonAction::Next(level) => {
+                        load_level(
+                            &mut commands,
+                            ldtk_project_assets.get(&world.0),
+                            *level,
+                            game_kind.get(),
+                        );
+                        match game_kind.get() {
+                            GameKind::Platformer => next_state.set(GameMode::Play),
+                            GameKind::Puzzle => next_state.set(GameMode::Edit),
+                        };
+                    }
                     ButtonAction::Menu => {
                         commands.entity(world_query.single()).despawn_recursive();
                         next_state.set(GameMode::Menu);